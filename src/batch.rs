@@ -0,0 +1,282 @@
+//! Bookkeeping for commands that process many files, so that one bad file
+//! doesn't stop the whole batch.
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use gib2sgf::{ConversionError, GibParseError, SgfParseError, SgfToGibError};
+use crate::cli::Verbosity;
+use crate::files::{collect_inputs, InputFile};
+
+/// What happened to a file that was processed successfully
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Outcome {
+    Done,
+    Skipped,
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    Conversion(ConversionError),
+    SgfToGib(SgfToGibError),
+    Sgf(SgfParseError),
+    /// The file was processed, but the result was not what was expected
+    Mismatch(String),
+    /// The input can't be used for the requested operation
+    InvalidInput(String),
+    /// Processing the file panicked, which is a bug
+    Panic(String),
+}
+
+pub type FileResult = Result<Outcome, FileError>;
+
+/// Messages about processing of a single file, printed when the file is done so that
+/// messages from parallel threads don't interleave.
+#[derive(Debug, Default)]
+pub struct Log {
+    lines: Vec<(bool, String)>,
+}
+
+impl Log {
+    pub fn out(&mut self, line: String) {
+        self.lines.push((false, line));
+    }
+
+    pub fn err(&mut self, line: String) {
+        self.lines.push((true, line));
+    }
+
+    pub fn print(self) {
+        for (is_error, line) in self.lines {
+            if is_error { eprintln!("{}", line) } else { println!("{}", line) }
+        }
+    }
+}
+
+pub struct Summary {
+    /// Verb describing successfully processed files, eg. "converted"
+    action: &'static str,
+    done: usize,
+    skipped: usize,
+    failed: Vec<(PathBuf, FileError)>,
+}
+
+impl Summary {
+    pub fn new(action: &'static str) -> Summary {
+        Summary { action, done: 0, skipped: 0, failed: Vec::new() }
+    }
+
+    /// Collects files from the inputs, recording inputs that can't be read as failures.
+    /// Files found through several inputs are included only once.
+    pub fn collect_inputs(&mut self, inputs: &[PathBuf], matches: &dyn Fn(&str) -> bool) -> Vec<InputFile> {
+        let mut files = Vec::new();
+        let mut seen = HashSet::new();
+
+        for input in inputs {
+            match collect_inputs(std::slice::from_ref(input), matches) {
+                Ok(found) => for file in found {
+                    if seen.insert(file.path.clone()) {
+                        files.push(file);
+                    }
+                },
+                Err(e) => self.record(input, Err(FileError::Io(e))),
+            }
+        }
+
+        files
+    }
+
+    /// Processes the files using given number of threads. Messages and results are
+    /// reported in the order of the files, regardless of which thread finishes first.
+    pub fn run<F>(&mut self, files: &[InputFile], jobs: usize, process: F)
+        where F: Fn(&InputFile, &mut Log) -> FileResult + Sync {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..jobs.clamp(1, files.len().max(1)) {
+                let sender = sender.clone();
+                let (next, process) = (&next, &process);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let file = match files.get(index) {
+                        Some(file) => file,
+                        None => break,
+                    };
+                    let mut log = Log::default();
+                    // A bug triggered by one file fails only that file
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| process(file, &mut log)))
+                        .unwrap_or_else(|payload| Err(FileError::Panic(panic_message(payload.as_ref()))));
+                    if sender.send((index, log, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            let mut finished = BTreeMap::new();
+            let mut reported = 0;
+            for (index, log, result) in receiver {
+                finished.insert(index, (log, result));
+
+                while let Some((log, result)) = finished.remove(&reported) {
+                    log.print();
+                    self.record(&files[reported].path, result);
+                    reported += 1;
+                }
+            }
+        });
+    }
+
+    /// Records the result of processing a file, reporting failures immediately
+    pub fn record(&mut self, path: &Path, result: FileResult) {
+        match result {
+            Ok(Outcome::Done) => self.done += 1,
+            Ok(Outcome::Skipped) => self.skipped += 1,
+            Err(e) => {
+                eprintln!("Error   {:?}: {}", path, e);
+                self.failed.push((path.to_path_buf(), e));
+            }
+        }
+    }
+
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// Prints the counts and the reasons of failures
+    pub fn print(&self, verbosity: Verbosity) {
+        if verbosity != Verbosity::Quiet {
+            println!("{} {}, {} skipped, {} failed", self.done, self.action, self.skipped, self.failed.len());
+        }
+
+        if !self.failed.is_empty() {
+            eprintln!("Failed files:");
+            for (path, e) in &self.failed {
+                eprintln!("  {:?}: {}", path, e);
+            }
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.failed.is_empty() { 0 } else { 1 }
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> FileError {
+        FileError::Io(e)
+    }
+}
+
+impl From<ConversionError> for FileError {
+    fn from(e: ConversionError) -> FileError {
+        FileError::Conversion(e)
+    }
+}
+
+impl From<GibParseError> for FileError {
+    fn from(e: GibParseError) -> FileError {
+        FileError::Conversion(ConversionError::Parse(e))
+    }
+}
+
+impl From<SgfToGibError> for FileError {
+    fn from(e: SgfToGibError) -> FileError {
+        FileError::SgfToGib(e)
+    }
+}
+
+impl From<SgfParseError> for FileError {
+    fn from(e: SgfParseError) -> FileError {
+        FileError::Sgf(e)
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "{}", e),
+            FileError::Conversion(e) => write!(f, "{}", e),
+            FileError::SgfToGib(e) => write!(f, "{}", e),
+            FileError::Sgf(e) => write!(f, "{}", e),
+            FileError::Mismatch(message) | FileError::InvalidInput(message) => write!(f, "{}", message),
+            FileError::Panic(message) => write!(f, "internal error: {}", message),
+        }
+    }
+}
+
+impl Error for FileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::new("converted");
+        summary.record(Path::new("a.gib"), Ok(Outcome::Done));
+        summary.record(Path::new("b.gib"), Ok(Outcome::Skipped));
+        assert_eq!(summary.exit_code(), 0);
+
+        let files = summary.collect_inputs(&[PathBuf::from("does-not-exist")], &|_| true);
+        assert!(files.is_empty());
+        assert_eq!(summary.done(), 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_run_reports_in_order() {
+        let files: Vec<InputFile> = (0..20).map(|i| InputFile { path: PathBuf::from(i.to_string()), relative_path: PathBuf::new() }).collect();
+        let mut summary = Summary::new("converted");
+        let order = std::sync::Mutex::new(Vec::new());
+
+        summary.run(&files, 4, |file, _| {
+            let index: u64 = file.path.to_str().unwrap().parse().unwrap();
+            thread::sleep(std::time::Duration::from_millis(20 - index));
+            order.lock().unwrap().push(index);
+            if index % 5 == 0 { Err(FileError::Mismatch(index.to_string())) } else { Ok(Outcome::Done) }
+        });
+
+        assert_eq!(order.lock().unwrap().len(), 20);
+        assert_eq!(summary.done(), 16);
+        let failed: Vec<_> = summary.failed.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(failed, vec![PathBuf::from("0"), PathBuf::from("5"), PathBuf::from("10"), PathBuf::from("15")]);
+    }
+
+    #[test]
+    fn test_run_survives_panics() {
+        let files: Vec<InputFile> = (0..4).map(|i| InputFile { path: PathBuf::from(i.to_string()), relative_path: PathBuf::new() }).collect();
+        let mut summary = Summary::new("converted");
+
+        summary.run(&files, 2, |file, _| {
+            if file.path == Path::new("1") {
+                panic!("bad file {}", 1);
+            }
+            Ok(Outcome::Done)
+        });
+
+        assert_eq!(summary.done(), 3);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, PathBuf::from("1"));
+        assert_eq!(summary.failed[0].1.to_string(), "internal error: bad file 1");
+    }
+}
@@ -0,0 +1,661 @@
+//! Parsing of command line arguments
+use std::collections::VecDeque;
+use std::io;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use gib2sgf::{ConversionOptions, LocalDate, Validation};
+use crate::index::{ResultFilter, SearchFilter, DEFAULT_INDEX};
+
+pub const USAGE: &str = "\
+Converts GIB files used by Tygem to SGF.
+
+Usage:
+    gib2sgf [convert] [OPTIONS] [PATH...]
+    gib2sgf [convert] [OPTIONS] [-o FILE] FILE|-
+    gib2sgf to-gib [OPTIONS] [PATH...]
+    gib2sgf watch [OPTIONS] DIR
+    gib2sgf verify [-j N] [-q|-v] [PATH...]
+    gib2sgf info [--format table|tsv|json] [PATH...]
+    gib2sgf index [-o FILE] [-q|-v] [PATH...]
+    gib2sgf search [--index FILE] [SEARCH OPTIONS]
+    gib2sgf stats --player NAME [--format text|json] [--index FILE | PATH...]
+    gib2sgf help
+
+Paths can be files or directories, which are searched recursively. By default
+the current directory is used. With `-` as the path, or without paths when input
+is piped, a single file is read from stdin and written to stdout.
+
+Commands:
+    convert     Convert GIB files to SGF files next to them (default)
+    to-gib      Convert SGF files to GIB files next to them
+    watch       Convert GIB files in DIR, and then new files as they are saved
+    verify      Check that existing SGF files match the current conversion
+    info        Print game information of GIB files
+    index       Write the metadata of GIB and SGF files to a CSV index
+    search      Print the games in the index that match all search options
+    stats       Print win/loss statistics of a player from GIB and SGF files, or an index
+
+Options:
+    -o, --output FILE       Write the conversion of a single file to FILE, or stdout with -
+    --output-dir DIR        Write converted files to DIR instead of next to the originals
+    -f, --force             Overwrite existing files
+    -n, --dry-run           Show what would be converted without writing anything
+    -q, --quiet             Only print errors
+    -v, --verbose           Print also skipped files
+    -j, --jobs N            Number of files processed in parallel, by default the number of CPUs
+    --format FORMAT         Output format: sgf or json for convert, table, tsv or json for info
+                            (json requires the 'serde' feature)
+    --rules RULES           Rules written to SGF: japanese, korean or chinese
+    --board-size N          Override the board size of the GIB files
+    --date-format FORMAT    Format of the date: date, datetime or none
+    --utc-offset OFFSET     Convert game time from Korean time to given offset, eg. +02:00
+    --property NAME=VALUE   Add or replace a game info property, eg. EV or GN
+    --no-ap                 Don't write the AP property
+    -h, --help              Print this help
+
+Options of index and search:
+    -o, --output FILE       Index file to write, by default gib2sgf-index.csv
+    --index FILE            Index file to search, by default gib2sgf-index.csv
+    --player NAME           Games where NAME played either colour
+    --from DATE, --to DATE  Games played on or after, or on or before DATE (YYYY-MM-DD)
+    --result RESULT         Games won or lost by the player with `win` or `loss`, or
+                            results starting with RESULT, eg. B+ or W+R
+    --rank RANK             Games where the player, or either player, had RANK, eg. 4D
+";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Convert(ConvertArgs),
+    ToGib(ConvertArgs),
+    Watch(ConvertArgs),
+    Verify(VerifyArgs),
+    Info(InfoArgs),
+    Index(IndexArgs),
+    Search(SearchArgs),
+    Stats(StatsArgs),
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OutputFormat {
+    Sgf,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InfoFormat {
+    Table,
+    Tsv,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConvertArgs {
+    pub inputs: Vec<PathBuf>,
+    /// Output file when converting a single file
+    pub output: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub force: bool,
+    /// Overwrite existing files that are older than their source, as files are rewritten when watching
+    pub update: bool,
+    pub dry_run: bool,
+    pub verbosity: Verbosity,
+    /// Number of files processed in parallel
+    pub jobs: usize,
+    pub format: OutputFormat,
+    pub options: ConversionOptions,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyArgs {
+    pub inputs: Vec<PathBuf>,
+    pub verbosity: Verbosity,
+    pub jobs: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InfoArgs {
+    pub inputs: Vec<PathBuf>,
+    pub format: InfoFormat,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IndexArgs {
+    pub inputs: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SearchArgs {
+    pub index: PathBuf,
+    pub filter: SearchFilter,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StatsFormat {
+    Text,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StatsArgs {
+    pub player: String,
+    pub inputs: Vec<PathBuf>,
+    /// Index to read the games from, instead of the inputs
+    pub index: Option<PathBuf>,
+    pub format: StatsFormat,
+}
+
+/// Name of standard input and output in paths
+pub const STDIO: &str = "-";
+
+/// Parses the arguments, not including the program name
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    parse_args_with_stdin(args, !io::stdin().is_terminal())
+}
+
+/// Parses the arguments, converting from stdin by default if `piped_stdin` is true
+fn parse_args_with_stdin(args: &[String], piped_stdin: bool) -> Result<Command, String> {
+    let mut args = Args { args: args.iter().cloned().collect(), help: false };
+    let command = match args.args.front().map(|a| a.as_str()) {
+        Some("convert") | Some("to-gib") | Some("watch") | Some("verify") | Some("info") | Some("index") | Some("search") | Some("stats") | Some("help") => args.args.pop_front().unwrap(),
+        Some("--to-gib") => { args.args.pop_front(); "to-gib".to_string() }
+        _ => "convert".to_string()
+    };
+
+    let result = match command.as_str() {
+        "convert" => parse_convert(&mut args, piped_stdin).map(Command::Convert),
+        "to-gib" => parse_convert(&mut args, piped_stdin).map(Command::ToGib),
+        "watch" => parse_watch(&mut args).map(Command::Watch),
+        "verify" => parse_verify(&mut args).map(Command::Verify),
+        "info" => parse_info(&mut args).map(Command::Info),
+        "index" => parse_index(&mut args).map(Command::Index),
+        "search" => parse_search(&mut args).map(Command::Search),
+        "stats" => parse_stats(&mut args).map(Command::Stats),
+        _ => Ok(Command::Help)
+    };
+
+    // Help wins over missing arguments, eg. `stats --help` without --player
+    if args.help { Ok(Command::Help) } else { result }
+}
+
+fn parse_convert(args: &mut Args, piped_stdin: bool) -> Result<ConvertArgs, String> {
+    let mut result = ConvertArgs {
+        inputs: Vec::new(),
+        output: None,
+        output_dir: None,
+        force: false,
+        update: false,
+        dry_run: false,
+        verbosity: Verbosity::Normal,
+        jobs: default_jobs(),
+        format: OutputFormat::Sgf,
+        options: ConversionOptions { validation: Validation::Warn, ..ConversionOptions::default() },
+    };
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg {
+            Arg::Input(path) => {
+                result.inputs.push(path);
+                continue;
+            }
+            Arg::Flag(flag, inline) => (flag, inline)
+        };
+
+        match flag.as_str() {
+            "-o" | "--output" => result.output = Some(PathBuf::from(args.value(&flag, inline)?)),
+            "--output-dir" => result.output_dir = Some(PathBuf::from(args.value(&flag, inline)?)),
+            "-f" | "--force" => result.force = true,
+            "-n" | "--dry-run" => result.dry_run = true,
+            "--format" => result.format = args.value(&flag, inline)?.parse()?,
+            "--rules" => result.options.rules = args.value(&flag, inline)?.parse()?,
+            "--board-size" => {
+                let size = args.value(&flag, inline)?;
+                let size = size.parse().map_err(|_| format!("invalid board size '{}'", size))?;
+                result.options.set_board_size(size)?;
+            }
+            "--date-format" => result.options.date_format = args.value(&flag, inline)?.parse()?,
+            "--utc-offset" => result.options.set_utc_offset(parse_utc_offset(&args.value(&flag, inline)?)?)?,
+            "--property" => {
+                let property = args.value(&flag, inline)?;
+                let (name, value) = property.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, got '{}'", property))?;
+                result.options.add_property(name, value)?;
+            }
+            "--no-ap" => result.options.include_application = false,
+            "-j" | "--jobs" => result.jobs = parse_jobs(&args.value(&flag, inline)?)?,
+            _ => result.verbosity = parse_verbosity(&flag)?,
+        }
+    }
+
+    if result.inputs.is_empty() && piped_stdin {
+        result.inputs.push(PathBuf::from(STDIO));
+    }
+    result.inputs = default_inputs(result.inputs);
+
+    let stdin = result.inputs.iter().any(|i| i == Path::new(STDIO));
+    if stdin && result.inputs.len() > 1 {
+        return Err("stdin can't be combined with other inputs".to_string());
+    }
+    if result.output.is_some() && result.inputs.len() > 1 {
+        return Err("--output requires a single input".to_string());
+    }
+    if result.output_dir.is_some() && (stdin || result.output.is_some()) {
+        return Err("--output-dir can't be used with --output or stdin".to_string());
+    }
+
+    Ok(result)
+}
+
+fn parse_watch(args: &mut Args) -> Result<ConvertArgs, String> {
+    let mut result = parse_convert(args, false)?;
+    result.update = true;
+    if result.inputs.len() != 1 || result.inputs[0] == Path::new(STDIO) || result.output.is_some() {
+        return Err("watch requires a single directory".to_string());
+    }
+    Ok(result)
+}
+
+fn parse_verify(args: &mut Args) -> Result<VerifyArgs, String> {
+    let mut result = VerifyArgs { inputs: Vec::new(), verbosity: Verbosity::Normal, jobs: default_jobs() };
+
+    while let Some(arg) = args.next() {
+        match arg {
+            Arg::Input(path) => result.inputs.push(path),
+            Arg::Flag(flag, inline) if flag == "-j" || flag == "--jobs" => result.jobs = parse_jobs(&args.value(&flag, inline)?)?,
+            Arg::Flag(flag, _) => result.verbosity = parse_verbosity(&flag)?,
+        }
+    }
+
+    result.inputs = default_inputs(result.inputs);
+    Ok(result)
+}
+
+fn parse_info(args: &mut Args) -> Result<InfoArgs, String> {
+    let mut inputs = Vec::new();
+    let mut format = InfoFormat::Table;
+
+    while let Some(arg) = args.next() {
+        match arg {
+            Arg::Input(path) => inputs.push(path),
+            Arg::Flag(flag, inline) if flag == "--format" => format = args.value(&flag, inline)?.parse()?,
+            Arg::Flag(flag, _) => return Err(unknown_flag(&flag)),
+        }
+    }
+
+    Ok(InfoArgs { inputs: default_inputs(inputs), format })
+}
+
+fn parse_index(args: &mut Args) -> Result<IndexArgs, String> {
+    let mut result = IndexArgs { inputs: Vec::new(), output: PathBuf::from(DEFAULT_INDEX), verbosity: Verbosity::Normal };
+
+    while let Some(arg) = args.next() {
+        match arg {
+            Arg::Input(path) => result.inputs.push(path),
+            Arg::Flag(flag, inline) if flag == "-o" || flag == "--output" => result.output = PathBuf::from(args.value(&flag, inline)?),
+            Arg::Flag(flag, _) => result.verbosity = parse_verbosity(&flag)?,
+        }
+    }
+
+    result.inputs = default_inputs(result.inputs);
+    Ok(result)
+}
+
+fn parse_search(args: &mut Args) -> Result<SearchArgs, String> {
+    let mut result = SearchArgs { index: PathBuf::from(DEFAULT_INDEX), filter: SearchFilter::default() };
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg {
+            Arg::Input(path) => return Err(format!("unexpected argument '{}'", path.display())),
+            Arg::Flag(flag, inline) => (flag, inline),
+        };
+        let value = args.value(&flag, inline)?;
+
+        match flag.as_str() {
+            "--index" => result.index = PathBuf::from(value),
+            "--player" => result.filter.player = Some(value),
+            "--from" => result.filter.from = Some(parse_date(&value)?),
+            "--to" => result.filter.to = Some(parse_date(&value)?),
+            "--rank" => result.filter.rank = Some(value),
+            "--result" => result.filter.result = Some(match value.to_ascii_lowercase().as_str() {
+                "win" => ResultFilter::Win,
+                "loss" => ResultFilter::Loss,
+                _ => ResultFilter::Prefix(value),
+            }),
+            _ => return Err(unknown_flag(&flag)),
+        }
+    }
+
+    let relative_result = matches!(result.filter.result, Some(ResultFilter::Win) | Some(ResultFilter::Loss));
+    if relative_result && result.filter.player.is_none() {
+        return Err("--result win and loss require --player".to_string());
+    }
+    Ok(result)
+}
+
+fn parse_stats(args: &mut Args) -> Result<StatsArgs, String> {
+    let mut player = None;
+    let mut result = StatsArgs { player: String::new(), inputs: Vec::new(), index: None, format: StatsFormat::Text };
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg {
+            Arg::Input(path) => {
+                result.inputs.push(path);
+                continue;
+            }
+            Arg::Flag(flag, inline) => (flag, inline),
+        };
+
+        match flag.as_str() {
+            "--player" => player = Some(args.value(&flag, inline)?),
+            "--index" => result.index = Some(PathBuf::from(args.value(&flag, inline)?)),
+            "--format" => result.format = args.value(&flag, inline)?.parse()?,
+            _ => return Err(unknown_flag(&flag)),
+        }
+    }
+
+    result.player = player.ok_or_else(|| "stats requires --player".to_string())?;
+    if result.index.is_some() && !result.inputs.is_empty() {
+        return Err("--index can't be combined with paths".to_string());
+    }
+    result.inputs = default_inputs(result.inputs);
+    Ok(result)
+}
+
+fn parse_date(str: &str) -> Result<LocalDate, String> {
+    LocalDate::parse_iso(str).map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", str))
+}
+
+fn parse_verbosity(flag: &str) -> Result<Verbosity, String> {
+    match flag {
+        "-q" | "--quiet" => Ok(Verbosity::Quiet),
+        "-v" | "--verbose" => Ok(Verbosity::Verbose),
+        _ => Err(unknown_flag(flag))
+    }
+}
+
+fn unknown_flag(flag: &str) -> String {
+    format!("unknown option '{}'", flag)
+}
+
+fn default_inputs(inputs: Vec<PathBuf>) -> Vec<PathBuf> {
+    if inputs.is_empty() { vec![PathBuf::from(".")] } else { inputs }
+}
+
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn parse_jobs(str: &str) -> Result<usize, String> {
+    str.parse().ok().filter(|&jobs| jobs > 0).ok_or_else(|| format!("invalid number of jobs '{}'", str))
+}
+
+/// Parses offsets like `+9`, `-05:30` or `0` to minutes
+fn parse_utc_offset(str: &str) -> Result<i32, String> {
+    let invalid = || format!("invalid UTC offset '{}'", str);
+    let (sign, rest) = match str.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, str.strip_prefix('+').unwrap_or(str)),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+
+    if hours <= 14 && minutes < 60 {
+        // The library checks the whole offset, eg. that +14:30 is too large
+        Ok(sign * (hours * 60 + minutes) as i32)
+    } else {
+        Err(invalid())
+    }
+}
+
+impl FromStr for InfoFormat {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<InfoFormat, String> {
+        match str {
+            "table" => Ok(InfoFormat::Table),
+            "tsv" => Ok(InfoFormat::Tsv),
+            #[cfg(feature = "serde")]
+            "json" => Ok(InfoFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("JSON output requires building with the 'serde' feature".to_string()),
+            _ => Err(format!("unknown format '{}', expected table, tsv or json", str)),
+        }
+    }
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<StatsFormat, String> {
+        match str {
+            "text" => Ok(StatsFormat::Text),
+            #[cfg(feature = "serde")]
+            "json" => Ok(StatsFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("JSON output requires building with the 'serde' feature".to_string()),
+            _ => Err(format!("unknown format '{}', expected text or json", str)),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<OutputFormat, String> {
+        match str {
+            "sgf" => Ok(OutputFormat::Sgf),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("JSON output requires building with the 'serde' feature".to_string()),
+            _ => Err(format!("unknown format '{}', expected sgf or json", str)),
+        }
+    }
+}
+
+impl ConvertArgs {
+    /// True if converting a single file to stdout or a given output file
+    pub fn is_single(&self) -> bool {
+        self.output.is_some() || self.inputs.iter().any(|i| i == Path::new(STDIO))
+    }
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Sgf => "sgf",
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+enum Arg {
+    Input(PathBuf),
+    /// Flag with the value given as `--flag=value`, if any
+    Flag(String, Option<String>),
+}
+
+struct Args {
+    args: VecDeque<String>,
+    /// True if `-h` or `--help` was given as an option, not as the value of another one
+    help: bool,
+}
+
+impl Args {
+    fn next(&mut self) -> Option<Arg> {
+        let mut arg = self.args.pop_front()?;
+        while arg == "-h" || arg == "--help" {
+            self.help = true;
+            arg = self.args.pop_front()?;
+        }
+
+        if !arg.starts_with('-') || arg == "-" {
+            Some(Arg::Input(PathBuf::from(arg)))
+        } else if let (true, Some((flag, value))) = (arg.starts_with("--"), arg.split_once('=')) {
+            Some(Arg::Flag(flag.to_string(), Some(value.to_string())))
+        } else {
+            Some(Arg::Flag(arg, None))
+        }
+    }
+
+    /// Returns the value of given flag, either from `--flag=value` or the next argument
+    fn value(&mut self, flag: &str, inline: Option<String>) -> Result<String, String> {
+        inline.or_else(|| self.args.pop_front()).ok_or_else(|| format!("missing value for {}", flag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gib2sgf::Rules;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args_with_stdin(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>(), false)
+    }
+
+    fn parse_convert(args: &[&str]) -> ConvertArgs {
+        match parse(args) {
+            Ok(Command::Convert(args)) => args,
+            other => panic!("expected convert, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_default_command() {
+        let args = parse_convert(&[]);
+        assert_eq!(args.inputs, vec![PathBuf::from(".")]);
+        assert_eq!(args.verbosity, Verbosity::Normal);
+        assert!(!args.force && !args.dry_run);
+        assert!(args.jobs >= 1);
+
+        assert_eq!(parse_convert(&["dir"]).inputs, vec![PathBuf::from("dir")]);
+    }
+
+    #[test]
+    fn test_convert() {
+        let args = parse_convert(&["convert", "a.gib", "--output-dir=out", "-f", "dir", "-n", "-q",
+                                   "--rules", "korean", "--no-ap", "--property", "EV=Cup", "-j", "3"]);
+
+        assert_eq!(args.inputs, vec![PathBuf::from("a.gib"), PathBuf::from("dir")]);
+        assert_eq!(args.output_dir, Some(PathBuf::from("out")));
+        assert!(args.force && args.dry_run);
+        assert_eq!(args.verbosity, Verbosity::Quiet);
+        assert_eq!(args.jobs, 3);
+        assert_eq!(args.options.rules, Rules::Korean);
+        assert!(!args.options.include_application);
+        assert_eq!(args.options.extra_properties, vec![("EV".to_string(), "Cup".to_string())]);
+    }
+
+    #[test]
+    fn test_stdin_and_output() {
+        let args = parse_convert(&["-", "-o", "out.sgf"]);
+        assert_eq!(args.inputs, vec![PathBuf::from("-")]);
+        assert_eq!(args.output, Some(PathBuf::from("out.sgf")));
+        assert!(args.is_single());
+
+        assert!(!parse_convert(&["a.gib"]).is_single());
+        assert!(parse_convert(&["a.gib", "--output=b.sgf"]).is_single());
+
+        assert!(!parse_convert(&[]).is_single());
+        match parse_args_with_stdin(&[], true) {
+            Ok(Command::Convert(args)) => assert_eq!(args.inputs, vec![PathBuf::from("-")]),
+            other => panic!("expected convert, got {:?}", other)
+        }
+        match parse_args_with_stdin(&["to-gib".to_string(), "dir".to_string()], true) {
+            Ok(Command::ToGib(args)) => assert_eq!(args.inputs, vec![PathBuf::from("dir")]),
+            other => panic!("expected to-gib, got {:?}", other)
+        }
+
+        assert_eq!(parse(&["-", "a.gib"]), Err("stdin can't be combined with other inputs".to_string()));
+        assert_eq!(parse(&["-o", "x.sgf", "a.gib", "b.gib"]), Err("--output requires a single input".to_string()));
+        assert_eq!(parse(&["--output-dir", "out", "-"]), Err("--output-dir can't be used with --output or stdin".to_string()));
+    }
+
+    #[test]
+    fn test_other_commands() {
+        assert_eq!(parse(&["verify", "-v", "dir", "--jobs=2"]), Ok(Command::Verify(VerifyArgs { inputs: vec![PathBuf::from("dir")], verbosity: Verbosity::Verbose, jobs: 2 })));
+        assert_eq!(parse(&["info", "a.gib"]), Ok(Command::Info(InfoArgs { inputs: vec![PathBuf::from("a.gib")], format: InfoFormat::Table })));
+        assert_eq!(parse(&["info", "--format=tsv"]), Ok(Command::Info(InfoArgs { inputs: vec![PathBuf::from(".")], format: InfoFormat::Tsv })));
+        assert!(matches!(parse(&["--to-gib", "dir"]), Ok(Command::ToGib(_))));
+        assert!(matches!(parse(&["watch", "--output-dir", "out", "dir"]), Ok(Command::Watch(ConvertArgs { update: true, .. }))));
+        assert!(matches!(parse(&["convert", "dir"]), Ok(Command::Convert(ConvertArgs { update: false, .. }))));
+        assert_eq!(parse(&["watch", "a", "b"]), Err("watch requires a single directory".to_string()));
+        assert_eq!(parse(&["help"]), Ok(Command::Help));
+        assert_eq!(parse(&["convert", "dir", "--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["stats", "-h"]), Ok(Command::Help));
+        assert_eq!(parse(&["search", "--help", "--player", "komula"]), Ok(Command::Help));
+
+        // Values of options are not taken as help
+        assert_eq!(parse_convert(&["--property", "GN=--help", "a.gib"]).options.extra_properties,
+                   vec![("GN".to_string(), "--help".to_string())]);
+        assert_eq!(parse_convert(&["-o", "-h", "a.gib"]).output, Some(PathBuf::from("-h")));
+    }
+
+    #[test]
+    fn test_index_and_search() {
+        assert_eq!(parse(&["index", "-o", "games.csv", "dir"]), Ok(Command::Index(IndexArgs {
+            inputs: vec![PathBuf::from("dir")], output: PathBuf::from("games.csv"), verbosity: Verbosity::Normal
+        })));
+
+        let filter = SearchFilter {
+            player: Some("komula".to_string()),
+            from: LocalDate::parse_iso("2020-01-01").ok(),
+            to: None,
+            result: Some(ResultFilter::Win),
+            rank: Some("4D".to_string()),
+        };
+        assert_eq!(parse(&["search", "--player", "komula", "--from=2020-01-01", "--result", "win", "--rank", "4D"]),
+                   Ok(Command::Search(SearchArgs { index: PathBuf::from(DEFAULT_INDEX), filter })));
+
+        assert_eq!(parse(&["search", "--result", "loss"]), Err("--result win and loss require --player".to_string()));
+        assert_eq!(parse(&["search", "--to", "2020-13-01"]), Err("invalid date '2020-13-01', expected YYYY-MM-DD".to_string()));
+        assert_eq!(parse(&["search", "komula"]), Err("unexpected argument 'komula'".to_string()));
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(parse(&["stats", "--player", "komula", "dir"]), Ok(Command::Stats(StatsArgs {
+            player: "komula".to_string(), inputs: vec![PathBuf::from("dir")], index: None, format: StatsFormat::Text
+        })));
+        assert!(matches!(parse(&["stats", "--player=komula", "--index", "games.csv"]), Ok(Command::Stats(StatsArgs { index: Some(_), .. }))));
+
+        assert_eq!(parse(&["stats", "dir"]), Err("stats requires --player".to_string()));
+        assert_eq!(parse(&["stats", "--player", "a", "--index", "games.csv", "dir"]), Err("--index can't be combined with paths".to_string()));
+        assert_eq!(parse(&["stats", "--player", "a", "--format", "csv"]), Err("unknown format 'csv', expected text or json".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse(&["--board-size"]), Err("missing value for --board-size".to_string()));
+        assert_eq!(parse(&["--board-size", "30"]), Err("invalid board size 30".to_string()));
+        assert_eq!(parse(&["--board-size", "x"]), Err("invalid board size 'x'".to_string()));
+        assert_eq!(parse(&["--utc-offset", "+14:30"]), Err("invalid UTC offset of 870 minutes".to_string()));
+        assert_eq!(parse(&["-j", "0"]), Err("invalid number of jobs '0'".to_string()));
+        assert_eq!(parse(&["info", "-v"]), Err("unknown option '-v'".to_string()));
+        assert_eq!(parse(&["--frobnicate"]), Err("unknown option '--frobnicate'".to_string()));
+        assert_eq!(parse(&["--format", "xml"]), Err("unknown format 'xml', expected sgf or json".to_string()));
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("0"), Ok(0));
+        assert_eq!(parse_utc_offset("+9"), Ok(540));
+        assert_eq!(parse_utc_offset("-05:30"), Ok(-330));
+        assert!(parse_utc_offset("+-5").is_err());
+        assert!(parse_utc_offset("9:60").is_err());
+    }
+}
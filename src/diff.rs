@@ -0,0 +1,152 @@
+//! Structural comparison of SGF files, used to verify conversions against earlier results
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use gib2sgf::{SgfCollection, SgfNode, SgfTree};
+
+/// Properties that are expected to change between versions
+const IGNORED_PROPERTIES: &[&str] = &["AP"];
+
+/// A single difference, with a location like `game 1, variation 2, node 3`
+#[derive(Debug, Eq, PartialEq)]
+pub struct Difference {
+    pub location: String,
+    pub kind: DifferenceKind,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DifferenceKind {
+    Missing(String),
+    Added(String),
+    Changed(String, String),
+}
+
+/// Compares the games node by node, ignoring the order of properties and of the
+/// values of list properties like `AB`
+pub fn diff_collections(old: &SgfCollection, new: &SgfCollection) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let count = old.trees().len().max(new.trees().len());
+
+    for i in 0..count {
+        let location = format!("game {}", i + 1);
+        diff_trees(old.trees().get(i), new.trees().get(i), &location, &mut differences);
+    }
+
+    differences
+}
+
+fn diff_trees(old: Option<&SgfTree>, new: Option<&SgfTree>, location: &str, differences: &mut Vec<Difference>) {
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new)) => (old, new),
+        (Some(old), None) => return differences.push(Difference::new(location, DifferenceKind::Missing(describe_tree(old)))),
+        (None, Some(new)) => return differences.push(Difference::new(location, DifferenceKind::Added(describe_tree(new)))),
+        (None, None) => return,
+    };
+
+    for i in 0..old.nodes().len().max(new.nodes().len()) {
+        let location = format!("{}, node {}", location, i);
+        match (old.nodes().get(i), new.nodes().get(i)) {
+            (Some(old), Some(new)) => diff_nodes(old, new, &location, differences),
+            (Some(old), None) => differences.push(Difference::new(&location, DifferenceKind::Missing(describe_node(old)))),
+            (None, Some(new)) => differences.push(Difference::new(&location, DifferenceKind::Added(describe_node(new)))),
+            (None, None) => {}
+        }
+    }
+
+    for i in 0..old.children().len().max(new.children().len()) {
+        let location = format!("{}, variation {}", location, i + 1);
+        diff_trees(old.children().get(i), new.children().get(i), &location, differences);
+    }
+}
+
+fn diff_nodes(old: &SgfNode, new: &SgfNode, location: &str, differences: &mut Vec<Difference>) {
+    let names: BTreeSet<&String> = old.properties().chain(new.properties())
+        .map(|(name, _)| name)
+        .filter(|name| !IGNORED_PROPERTIES.contains(&name.as_str()))
+        .collect();
+
+    for name in names {
+        let kind = match (property_values(old, name), property_values(new, name)) {
+            (Some(old), Some(new)) if old == new => continue,
+            (Some(old), Some(new)) => DifferenceKind::Changed(describe_property(name, &old), describe_property(name, &new)),
+            (Some(old), None) => DifferenceKind::Missing(describe_property(name, &old)),
+            (None, Some(new)) => DifferenceKind::Added(describe_property(name, &new)),
+            (None, None) => continue,
+        };
+        differences.push(Difference::new(location, kind));
+    }
+}
+
+fn property_values<'a>(node: &'a SgfNode, name: &str) -> Option<Vec<&'a str>> {
+    let mut values: Vec<&str> = node.get_property_list(name)?.iter().map(|v| v.as_str()).collect();
+    values.sort_unstable();
+    Some(values)
+}
+
+fn describe_property(name: &str, values: &[&str]) -> String {
+    let values: String = values.iter().map(|v| format!("[{}]", v)).collect();
+    format!("{}{}", name, values)
+}
+
+fn describe_node(node: &SgfNode) -> String {
+    let properties: Vec<String> = node.properties()
+        .map(|(name, values)| describe_property(name, &values.iter().map(|v| v.as_str()).collect::<Vec<_>>()))
+        .collect();
+    format!(";{}", properties.join(""))
+}
+
+fn describe_tree(tree: &SgfTree) -> String {
+    format!("{} nodes", tree.nodes().len())
+}
+
+impl Difference {
+    fn new(location: &str, kind: DifferenceKind) -> Difference {
+        Difference { location: location.to_string(), kind }
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            DifferenceKind::Missing(old) => write!(f, "{}: - {}", self.location, old),
+            DifferenceKind::Added(new) => write!(f, "{}: + {}", self.location, new),
+            DifferenceKind::Changed(old, new) => write!(f, "{}: {} -> {}", self.location, old, new),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        let old = SgfCollection::parse(old).unwrap();
+        let new = SgfCollection::parse(new).unwrap();
+        diff_collections(&old, &new).iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_equal() {
+        assert!(diff("(;GM[1]AP[gib2sgf:0.1.0]KM[6.5];B[pd])", "(;KM[6.5]GM[1]AP[gib2sgf:0.1.2];B[pd])").is_empty());
+        assert!(diff("(;AB[dd][pp])", "(;AB[pp][dd])").is_empty());
+    }
+
+    #[test]
+    fn test_differences() {
+        assert_eq!(diff("(;GM[1]KM[6.5]RE[B+R];B[pd];W[dd])", "(;GM[1]KM[0]DT[2020-01-01];B[pd];W[dc];B[pp])"), vec![
+            "game 1, node 0: + DT[2020-01-01]",
+            "game 1, node 0: KM[6.5] -> KM[0]",
+            "game 1, node 0: - RE[B+R]",
+            "game 1, node 2: W[dd] -> W[dc]",
+            "game 1, node 3: + ;B[pp]",
+        ]);
+    }
+
+    #[test]
+    fn test_variations_and_games() {
+        assert_eq!(diff("(;GM[1](;B[pd])(;B[dd]))", "(;GM[1](;B[pd]))(;GM[1])"), vec![
+            "game 1, variation 2: - 1 nodes",
+            "game 2: + 1 nodes",
+        ]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Detecting the character encoding of GIB files.
+//!
+//! Tygem clients save files in the legacy encoding of the user's locale, which for most
+//! players means CP949, so names and places can't be assumed to be UTF-8.
+use std::borrow::Cow;
+use encoding_rs::{Encoding, UTF_8, EUC_KR, SHIFT_JIS, GB18030};
+
+/// Legacy encodings to try, in order of preference when several of them fit equally well.
+/// encoding_rs implements EUC-KR as its superset CP949 (Unified Hangul Code).
+const CANDIDATES: [&Encoding; 3] = [EUC_KR, SHIFT_JIS, GB18030];
+
+/// Decodes contents of a GIB file, detecting the encoding from BOM, UTF-8 validity
+/// or the script of the characters that legacy encodings would produce.
+pub fn decode_gib(bytes: &[u8]) -> (Cow<'_, str>, &'static Encoding) {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return (text, encoding);
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (Cow::Borrowed(text), UTF_8);
+    }
+
+    let mut best: Option<(isize, Cow<str>, &'static Encoding)> = None;
+    for &encoding in CANDIDATES.iter() {
+        if let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+            let score = script_score(encoding, &text);
+            if best.as_ref().map(|(best_score, _, _)| score > *best_score).unwrap_or(true) {
+                best = Some((score, text, encoding));
+            }
+        }
+    }
+
+    match best {
+        Some((_, text, encoding)) => (text, encoding),
+        None => {
+            // Nothing decodes cleanly, so assume a Korean file with some corruption
+            let (text, _) = EUC_KR.decode_without_bom_handling(bytes);
+            (text, EUC_KR)
+        }
+    }
+}
+
+/// Scores how well decoded text fits the language of the encoding: characters typical for
+/// the language count for it and other non-ASCII characters against it. Punctuation and
+/// fullwidth forms are used in all of the languages, so they are neutral.
+fn script_score(encoding: &Encoding, text: &str) -> isize {
+    let hangul = |c| ('\u{AC00}'..='\u{D7A3}').contains(&c) || ('\u{3130}'..='\u{318F}').contains(&c);
+    let kana = |c| ('\u{3040}'..='\u{30FF}').contains(&c);
+    let ideograph = |c| ('\u{4E00}'..='\u{9FFF}').contains(&c);
+    let neutral = |c| ('\u{3000}'..='\u{303F}').contains(&c) || ('\u{FF01}'..='\u{FF5E}').contains(&c);
+
+    let typical = |c| if encoding == EUC_KR {
+        hangul(c)
+    } else if encoding == SHIFT_JIS {
+        kana(c) || ideograph(c)
+    } else {
+        ideograph(c)
+    };
+
+    text.chars().filter(|&c| !c.is_ascii() && !neutral(c)).map(|c| if typical(c) { 1 } else { -1 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(text: &str, encoding: &'static Encoding) -> (String, &'static str) {
+        let (bytes, _, _) = encoding.encode(text);
+        let (decoded, detected) = decode_gib(&bytes);
+        (decoded.into_owned(), detected.name())
+    }
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(decode_gib("\\[GAMEBLACKNAME=흑돌 (2D)\\]".as_bytes()), (Cow::Borrowed("\\[GAMEBLACKNAME=흑돌 (2D)\\]"), UTF_8));
+        assert_eq!(decode_gib(b"\xEF\xBB\xBFfoo"), (Cow::Borrowed("foo"), UTF_8));
+    }
+
+    #[test]
+    fn test_utf16_bom() {
+        assert_eq!(decode_gib(b"\xFF\xFEf\x00o\x00"), (Cow::Borrowed("fo"), encoding_rs::UTF_16LE));
+    }
+
+    #[test]
+    fn test_legacy_encodings() {
+        assert_eq!(detect("\\[GAMEPLACE=타이젬 바둑\\]", EUC_KR), ("\\[GAMEPLACE=타이젬 바둑\\]".to_string(), "EUC-KR"));
+        assert_eq!(detect("\\[GAMEWHITENAME=똠방각하 (3D)\\]", EUC_KR), ("\\[GAMEWHITENAME=똠방각하 (3D)\\]".to_string(), "EUC-KR"));
+        assert_eq!(detect("\\[GAMEPLACE=タイゼム囲碁\\]", SHIFT_JIS), ("\\[GAMEPLACE=タイゼム囲碁\\]".to_string(), "Shift_JIS"));
+        assert_eq!(detect("\\[GAMEPLACE=弈城围棋网络对局\\]", GB18030), ("\\[GAMEPLACE=弈城围棋网络对局\\]".to_string(), "gb18030"));
+    }
+}
@@ -1,31 +1,73 @@
 use std::{fs, io};
 use std::path::{Path, PathBuf};
 
-pub fn collect_files(dir: &Path) -> io::Result<Vec<Box<Path>>> {
+/// A file given on the command line, or found from a directory given on the command line
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Path relative to the directory it was found from, or just the file name
+    pub relative_path: PathBuf,
+}
+
+pub fn is_gib_file(name: &str) -> bool {
+    name.ends_with(".gib") || name.ends_with(".gib.dll")
+}
+
+pub fn is_sgf_file(name: &str) -> bool {
+    name.ends_with(".sgf")
+}
+
+/// Collects the given files and matching files from given directories, recursively
+pub fn collect_inputs(inputs: &[PathBuf], matches: &dyn Fn(&str) -> bool) -> io::Result<Vec<InputFile>> {
     let mut result = Vec::new();
-    collect_files_into(dir, &mut result)?;
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut files = Vec::new();
+            collect_files_into(input, matches, &mut files)?;
+            files.sort();
+
+            for path in files {
+                let relative_path = path.strip_prefix(input).unwrap_or(&path).to_path_buf();
+                result.push(InputFile { path, relative_path });
+            }
+        } else if input.is_file() {
+            let relative_path = input.file_name().map(PathBuf::from).unwrap_or_else(|| input.clone());
+            result.push(InputFile { path: input.clone(), relative_path });
+        } else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no such file or directory"));
+        }
+    }
+
     Ok(result)
 }
 
-fn collect_files_into(dir: &Path, result: &mut Vec<Box<Path>>) -> io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let name = path.file_name().map(|s| s.to_str()).flatten().unwrap_or("");
-            if path.is_dir() {
-                collect_files_into(&path, result)?;
-            } else if path.is_file() && name.ends_with(".gib") || name.ends_with(".gib.dll") {
-                result.push(path.into_boxed_path());
-            }
+fn collect_files_into(dir: &Path, matches: &dyn Fn(&str) -> bool, result: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if path.is_dir() {
+            collect_files_into(&path, matches, result)?;
+        } else if path.is_file() && matches(name) {
+            result.push(path);
         }
     }
     Ok(())
 }
 
+/// Returns the path for the converted file: next to the original file, or at the
+/// same relative path in the output directory.
+pub fn output_path(file: &InputFile, output_dir: Option<&Path>, extension: &str) -> PathBuf {
+    let path = match output_dir {
+        Some(dir) => dir.join(&file.relative_path),
+        None => file.path.clone()
+    };
+    normalize_path(&path).with_extension(extension)
+}
+
 /// For some reason Tygem files names can end up with all kinds of junk
 pub fn normalize_path(path: &Path) -> PathBuf {
-    if let Some(str) = path.file_name().map(|n| n.to_str()).flatten() {
+    if let Some(str) = path.file_name().and_then(|n| n.to_str()) {
         path.with_file_name(normalize_file_name(str))
     } else {
         path.to_path_buf()
@@ -63,4 +105,23 @@ mod tests {
         assert_eq!(normalize_file_name("foo.gib.dll.gib"), "foo.gib");
         assert_eq!(normalize_file_name("foo.gib..gib"), "foo.gib");
     }
+
+    #[test]
+    fn test_collect_inputs() {
+        let dir = std::env::temp_dir().join(format!("gib2sgf-files-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.gib"), "").unwrap();
+        fs::write(dir.join("sub/b.gib.dll"), "").unwrap();
+        fs::write(dir.join("c.sgf"), "").unwrap();
+
+        let files = collect_inputs(&[dir.clone(), dir.join("c.sgf")], &is_gib_file).unwrap();
+        let relative: Vec<_> = files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(relative, vec![PathBuf::from("a.gib"), PathBuf::from("sub/b.gib.dll"), PathBuf::from("c.sgf")]);
+
+        assert_eq!(output_path(&files[1], None, "sgf"), dir.join("sub/b.sgf"));
+        assert_eq!(output_path(&files[1], Some(Path::new("out")), "sgf"), PathBuf::from("out/sub/b.sgf"));
+        assert!(collect_inputs(&[dir.join("missing")], &is_gib_file).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -4,69 +4,179 @@
 //! https://senseis.xmp.net/?GIB
 //!
 //! Things not yet supported:
-//!   - time settings
 //!   - probably many different interesting things
 //!
 use std::collections::HashMap;
-use crate::go::{PlayerColor, Score, GameResult, Handicap, GoMove};
-use crate::time::LocalDate;
+use crate::go::{PlayerColor, Score, GameResult, Handicap, GoMove, TimeControl, ByoYomi, TimeLeft, BoardCoordinate, BOARD_SIZES};
+use crate::time::{LocalDate, LocalDateTime};
 use crate::lexer::Lexer;
+use crate::gib_document::{GibDocument, GibRecord, GibParseError, GibParseErrorKind, LineError, LineResult};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Gib {
     raw_attributes: HashMap<String, String>,
     handicap: Option<Handicap>,
+    /// Handicap stones placed freely by black, if the game did not use the fixed placement
+    free_handicap_stones: Option<Vec<BoardCoordinate>>,
     moves: Vec<GoMove>,
+    /// Clock state after each move, indexed like `moves`
+    times_left: Vec<Option<TimeLeft>>,
+    /// Problems that don't prevent reading the game, like an invalid date
+    #[cfg_attr(feature = "serde", serde(skip))]
+    warnings: Vec<GibParseError>,
 }
 
-type GibResult<T> = Result<T, GibParseError>;
+/// Game information interpreted from the header of a GIB file
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GibMetadata {
+    pub black_nick: Option<String>,
+    pub black_rank: Option<String>,
+    pub white_nick: Option<String>,
+    pub white_rank: Option<String>,
+    pub komi: Option<Score>,
+    pub result: Option<GameResult>,
+    pub date: Option<LocalDate>,
+    pub place: Option<String>,
+    pub board_size: u8,
+    pub handicap: Option<Handicap>,
+    pub time_control: Option<TimeControl>,
+}
 
-pub type GibParseError = String;
+type GibResult<T> = Result<T, GibParseError>;
 
 impl Gib {
     pub fn parse(str: &str) -> GibResult<Gib> {
+        Gib::from_document(&GibDocument::parse(str))
+    }
+
+    /// Interprets the lines of a GIB document. Fails on the first line that could not be parsed.
+    pub fn from_document(document: &GibDocument) -> GibResult<Gib> {
+        if let Some(e) = document.errors().first() {
+            return Err(e.clone());
+        }
+
         let mut raw_attributes = HashMap::new();
         let mut moves = Vec::new();
+        let mut times_left = Vec::new();
         let mut handicap: Option<Handicap> = None;
+        let mut handicap_line = None;
+        let mut warnings = Vec::new();
+
+        // Technically the attributes are in the header section and moves in the game section.
+        // We don't care about that and just loop through everything looking for known records.
+
+        for line in document.lines() {
+            match &line.record {
+                GibRecord::Attribute { key, value } => {
+                    // The date is left out, but the game can still be read
+                    if key == "GAMEDATE" && !value.trim().is_empty() {
+                        if let Err(e) = parse_gib_date(value) {
+                            let value_pos = line.text.find('=').map(|i| i + 1).unwrap_or(0);
+                            warnings.push(LineError { pos: value_pos + e.pos, ..e }.at(line.number, &line.text));
+                        }
+                    }
+                    raw_attributes.insert(key.clone(), value.clone());
+                }
+                &GibRecord::Stone { player, coordinate, time_left, .. } => {
+                    moves.push(GoMove::PlaceStone { player, coordinate });
+                    times_left.push(time_left);
+                }
+                GibRecord::Skip => {
+                    // Passes don't say who passed, so use the player whose turn it is
+                    moves.push(GoMove::Pass { player: next_player(&moves, handicap) });
+                    times_left.push(None);
+                }
+                &GibRecord::Init { handicap: stones } => {
+                    handicap = Handicap::from(stones);
+                    handicap_line = Some(line);
+                }
+                GibRecord::Unknown => if let Some(name) = unknown_section(&line.text) {
+                    warnings.push(LineError { pos: 0, kind: GibParseErrorKind::UnknownSection(name.to_string()) }.at(line.number, &line.text));
+                },
+                GibRecord::SectionMarker => {}
+            }
+        }
 
-        // Technically there's a separate header section separated by "\HS" and "\HE" lines
-        // and a game section separated by "\GS" and "\GE" lines. We don't care about that
-        // and just loop through everything looking for lines that match our patterns.
-
-        for line in str.lines() {
-            if line.starts_with(r"\[") {
-                let (key, value) = parse_metadata_line(line)?;
-                raw_attributes.insert(key.to_string(), value.to_string());
-            } else if line.starts_with("STO ") {
-                moves.push(parse_place_stone(line)?);
-            } else if line.starts_with("SKI") {
-                // TODO: pass
-            } else if line.starts_with("INI ") {
-                handicap = parse_handicap_line(line)?;
-            } else if line == r"\HS" || line == r"\HE" || line == r"\GS" || line == r"\GE" {
-                // ignore known
-            } else if line == "2 1 0" || line == "2 5 0" || line.ends_with("&4") {
-                // ignore known 2
-            } else {
-                // ignore for now
+        // With fixed placement white makes the first move. With free placement the
+        // handicap stones are recorded as black moves before it.
+        let mut free_handicap_stones = None;
+        if let Some(stones) = handicap.map(|h| h.stones() as usize) {
+            if let Some(points) = leading_black_stones(&moves, stones) {
+                moves.drain(..stones);
+                times_left.drain(..stones);
+                free_handicap_stones = Some(points);
             }
         }
 
-        Ok(Gib { raw_attributes, handicap, moves })
+        let gib = Gib { raw_attributes, handicap, free_handicap_stones, moves, times_left, warnings };
+        if let (Some(line), None) = (handicap_line, &gib.free_handicap_stones) {
+            // Fails if the fixed placement doesn't fit on the board
+            gib.get_handicap_points().map_err(|e| {
+                let pos = line.text.match_indices(' ').nth(2).map(|(i, _)| i + 1).unwrap_or(0);
+                LineError { pos, kind: GibParseErrorKind::InvalidHandicap(e) }.at(line.number, &line.text)
+            })?;
+        }
+
+        Ok(gib)
+    }
+
+    /// Returns the problems found when parsing that were ignored, eg. invalid dates
+    pub fn get_warnings(&self) -> &[GibParseError] {
+        &self.warnings
     }
 
     pub fn get_moves(&self) -> &Vec<GoMove> {
         &self.moves
     }
 
+    /// Returns the time left for the player after given move, if the file records it
+    pub fn get_time_left(&self, move_index: usize) -> Option<TimeLeft> {
+        self.times_left.get(move_index).copied().flatten()
+    }
+
     pub fn get_handicap(&self) -> Option<Handicap> {
         self.handicap
     }
 
-    fn get_attribute(&self, name: &str) -> Option<&str> {
+    /// Returns the points of the handicap stones, either placed freely or using the fixed placement
+    pub fn get_handicap_points(&self) -> Result<Vec<BoardCoordinate>, String> {
+        self.get_handicap_points_on(self.get_board_size())
+    }
+
+    /// Like `get_handicap_points`, but places fixed handicaps on a board of given size.
+    /// Fails if the fixed placement doesn't fit on the board.
+    pub fn get_handicap_points_on(&self, board_size: u8) -> Result<Vec<BoardCoordinate>, String> {
+        match (&self.free_handicap_stones, self.handicap) {
+            (Some(points), _) => Ok(points.clone()),
+            (None, Some(handicap)) => handicap.handicap_points(board_size),
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the board size from the header. Files without the header are 19x19, unless
+    /// the stones fit on 9x9 or 13x13 and reach its last line, which is unlikely for a game
+    /// on a larger board.
+    pub fn get_board_size(&self) -> u8 {
+        match self.get_attribute("GAMEBOARDSIZE") {
+            Some(size) => size.trim().parse().ok().filter(|size| BOARD_SIZES.contains(size)).unwrap_or(19),
+            None => guess_board_size(&self.moves, self.free_handicap_stones.as_deref().unwrap_or_default()),
+        }
+    }
+
+    /// Returns the raw value of a header attribute, eg. `GAMEPLACE`
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
         self.raw_attributes.get(name).map(|s| s.as_str())
     }
 
+    /// Returns all header attributes, in no particular order
+    pub fn get_attributes(&self) -> impl Iterator<Item=(&str, &str)> {
+        self.raw_attributes.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
     pub fn get_nick(&self, color: PlayerColor) -> Option<&str> {
         let attribute = self.get_attribute(color.pick("GAMEBLACKNAME", "GAMEWHITENAME"))?;
         parse_gib_name(attribute).0
@@ -78,20 +188,55 @@ impl Gib {
     }
 
     pub fn get_komi(&self) -> Option<Score> {
-        self.get_attribute("GAMEGONGJE").map(Score::from_gib).flatten()
+        self.get_attribute("GAMEGONGJE").and_then(Score::from_gib)
     }
 
     pub fn get_result(&self) -> Option<GameResult> {
-        self.get_attribute("GAMEINFOMAIN").map(GameResult::from_gib).flatten()
+        self.get_attribute("GAMEINFOMAIN").and_then(GameResult::from_gib)
+    }
+
+    pub fn get_time_control(&self) -> Option<TimeControl> {
+        TimeControl::from_gib(&self.get_gtime()?)
+    }
+
+    /// Returns the raw `GTIME` settings if `get_time_control` could only interpret the main time
+    pub fn get_unknown_time_settings(&self) -> Option<String> {
+        self.get_gtime().filter(|gtime| matches!(TimeControl::parse_gib(gtime), Some((_, false))))
+    }
+
+    fn get_gtime(&self) -> Option<String> {
+        parse_info_attributes(self.get_attribute("GAMEINFOMAIN")?).remove("GTIME")
     }
 
     pub fn get_date(&self) -> Option<LocalDate> {
-        self.get_attribute("GAMEDATE").map(|d| parse_gib_date(d).ok()).flatten()
+        self.get_attribute("GAMEDATE").and_then(|d| parse_gib_date(d).ok())
+    }
+
+    /// Returns the start time of the game, as recorded by Tygem in Korean time
+    pub fn get_date_time(&self) -> Option<LocalDateTime> {
+        self.get_attribute("GAMEDATE").and_then(parse_gib_date_time)
     }
 
     pub fn get_game_place(&self) -> Option<&str> {
         self.get_attribute("GAMEPLACE")
     }
+
+    pub fn get_metadata(&self) -> GibMetadata {
+        let owned = |s: Option<&str>| s.map(|s| s.to_string());
+        GibMetadata {
+            black_nick: owned(self.get_nick(PlayerColor::Black)),
+            black_rank: owned(self.get_rank(PlayerColor::Black)),
+            white_nick: owned(self.get_nick(PlayerColor::White)),
+            white_rank: owned(self.get_rank(PlayerColor::White)),
+            komi: self.get_komi(),
+            result: self.get_result(),
+            date: self.get_date(),
+            place: owned(self.get_game_place()),
+            board_size: self.get_board_size(),
+            handicap: self.get_handicap(),
+            time_control: self.get_time_control(),
+        }
+    }
 }
 
 impl Score {
@@ -101,36 +246,45 @@ impl Score {
     }
 }
 
-impl PlayerColor {
-    fn from_gib(s: &str) -> Result<PlayerColor, GibParseError> {
-        match s {
-            "1" => Ok(PlayerColor::Black),
-            "2" => Ok(PlayerColor::White),
-            _ => Err(format!("invalid player: '{}'", s))
-        }
+impl TimeControl {
+    /// Parses time settings of form `<main time>-<byo-yomi seconds>-<byo-yomi periods>`, where
+    /// seconds without periods are a Fischer increment. Only the main time is returned for
+    /// other variants, since it is known even if the rest can't be interpreted.
+    fn from_gib(gtime: &str) -> Option<TimeControl> {
+        TimeControl::parse_gib(gtime).map(|(time_control, _)| time_control)
     }
-}
-
-impl GameResult {
-    fn parse_info_attributes(s: &str) -> HashMap<String, String> {
-        let mut result = HashMap::new();
 
-        for part in s.split(',') {
-            if let Some(pos) = part.find(':') {
-                let key = &part[..pos];
-                let value = &part[pos + 1..];
+    /// Parses time settings like `from_gib`, also returning whether all of them were interpreted
+    fn parse_gib(gtime: &str) -> Option<(TimeControl, bool)> {
+        let mut fields = gtime.split('-');
+        let main_time = fields.next()?.parse::<u32>().ok()?;
+        let rest: Option<Vec<u32>> = fields.map(|field| field.parse().ok()).collect();
+
+        let time_control = match rest.as_deref() {
+            Some([]) | Some([0, 0]) => TimeControl::new(main_time, None),
+            Some(&[period_time, periods]) if period_time > 0 && periods > 0 =>
+                TimeControl::new(main_time, ByoYomi::from(periods, period_time)),
+            Some(&[increment, 0]) => TimeControl::with_increment(main_time, increment),
+            _ => return Some((TimeControl::new(main_time, None), false)),
+        };
+        Some((time_control, true))
+    }
 
-                result.insert(key.to_string(), value.to_string());
-            }
+    /// Formats the settings as `GTIME`, so that `from_gib` returns the same settings
+    pub(crate) fn to_gib(self) -> String {
+        match (self.byo_yomi(), self.increment()) {
+            (Some(byo_yomi), _) => format!("{}-{}-{}", self.main_time(), byo_yomi.period_time(), byo_yomi.periods()),
+            (None, Some(increment)) => format!("{}-{}-0", self.main_time(), increment),
+            (None, None) => format!("{}-0-0", self.main_time()),
         }
-
-        result
     }
+}
 
+impl GameResult {
     fn from_gib(game_info_main: &str) -> Option<GameResult> {
-        let attributes = GameResult::parse_info_attributes(game_info_main);
+        let attributes = parse_info_attributes(game_info_main);
 
-        let score = attributes.get("ZIPSU").map(|s| Score::from_gib(s.as_str())).flatten();
+        let score = attributes.get("ZIPSU").and_then(|s| Score::from_gib(s.as_str()));
         let grlt = attributes.get("GRLT")?.as_str();
         match grlt {
             "0" => Some(GameResult::Count(PlayerColor::Black, score)),
@@ -144,19 +298,52 @@ impl GameResult {
     }
 }
 
+/// Parses the comma separated `key:value` pairs of `GAMEINFOMAIN` attribute
+fn parse_info_attributes(s: &str) -> HashMap<String, String> {
+    let mut result = HashMap::new();
+
+    for part in s.split(',') {
+        if let Some(pos) = part.find(':') {
+            let key = &part[..pos];
+            let value = &part[pos + 1..];
+
+            result.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    result
+}
+
 /// Parses gib-formatted date into a LocalDate
-fn parse_gib_date(str: &str) -> GibResult<LocalDate> {
+fn parse_gib_date(str: &str) -> LineResult<LocalDate> {
+    let invalid_date = LineError::from_lexer(GibParseErrorKind::InvalidDate);
     let mut lexer = Lexer::new(str);
 
-    let year = lexer.read_number::<u16>()?;
-    lexer.expect("-")?;
+    let year = lexer.read_number::<u16>().map_err(invalid_date)?;
+    lexer.expect("-").map_err(invalid_date)?;
     lexer.optional(" ");
-    let month = lexer.read_number::<u8>()?;
-    lexer.expect("-")?;
+    let month = lexer.read_number::<u8>().map_err(invalid_date)?;
+    lexer.expect("-").map_err(invalid_date)?;
     lexer.optional(" ");
-    let day = lexer.read_number::<u8>()?;
+    let day = lexer.read_number::<u8>().map_err(invalid_date)?;
 
-    LocalDate::ymd(year, month, day)
+    LocalDate::ymd(year, month, day).map_err(|e| LineError { pos: 0, kind: GibParseErrorKind::InvalidDate(e) })
+}
+
+/// Returns the name of an unknown line that looks like a section marker, eg. `XS` for `\XS`
+fn unknown_section(line: &str) -> Option<&str> {
+    line.strip_prefix('\\').filter(|name| name.len() == 2 && (name.ends_with('S') || name.ends_with('E')))
+}
+
+/// Parses the time part of `GAMEDATE`, eg. `2020- 3-13-23-21-56`
+fn parse_gib_date_time(str: &str) -> Option<LocalDateTime> {
+    let date = parse_gib_date(str).ok()?;
+    let time: Vec<u8> = str.split('-').skip(3).map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+
+    match time[..] {
+        [hour, minute, second] => LocalDateTime::new(date, hour, minute, second).ok(),
+        _ => None
+    }
 }
 
 /// Extract name and rank from name attribute of form `name (rank)`.
@@ -176,56 +363,42 @@ fn parse_gib_name(str: &str) -> (Option<&str>, Option<&str>) {
     (Some(str), None)
 }
 
-/// Extract key and value from line of form `\[key=value\]`
-fn parse_metadata_line(str: &str) -> GibResult<(&str, &str)> {
-    let trimmed = str.trim();
-    if trimmed.starts_with(r"\[") && trimmed.ends_with(r"\]") {
-        let contents = &trimmed[2..trimmed.len() - 2];
-        if let Some(index) = contents.find('=') {
-            let name = &contents[..index];
-            let value = &contents[index + 1..];
-            return Ok((name, value));
-        }
+/// Returns the points of the first `count` moves if they all are black stones
+fn leading_black_stones(moves: &[GoMove], count: usize) -> Option<Vec<BoardCoordinate>> {
+    if moves.len() < count {
+        return None;
     }
-    Err(format!("invalid metadata line: '{}'", str))
-}
 
-/// Extract move from line of form `STO <num> <num> <player> <x> <y>`
-///
-/// Assumes that the moves are in order and we don't need move numbers for anything
-fn parse_place_stone(str: &str) -> GibResult<GoMove> {
-    let mut lexer = Lexer::new(str);
-    lexer.expect("STO ")?;
-
-    lexer.read_number::<i16>()?;
-    lexer.expect(" ")?;
-    lexer.read_number::<i16>()?;
-    lexer.expect(" ")?;
-    let player = PlayerColor::from_gib(lexer.read_while(|c| c != ' '))?;
-    lexer.expect(" ")?;
-    let x = lexer.read_number::<u8>()?;
-    lexer.expect(" ")?;
-    let y = lexer.read_number::<u8>()?;
-
-    Ok(GoMove::place_stone(player, (x, y)))
+    moves[..count].iter().map(|mv| match *mv {
+        GoMove::PlaceStone { player: PlayerColor::Black, coordinate } => Some(coordinate),
+        _ => None
+    }).collect()
 }
 
-/// Extract handicap from line of form `INI <num> <num> <handicap>`
-fn parse_handicap_line(str: &str) -> GibResult<Option<Handicap>> {
-    let trimmed = str.trim();
-
-    let prefix = "INI ";
-    let mut lexer = Lexer::new(&trimmed[prefix.len()..]);
-
-    lexer.read_number::<i16>()?;
-    lexer.expect(" ")?;
-
-    lexer.read_number::<i16>()?;
-    lexer.expect(" ")?;
-
-    let stones = lexer.read_number::<u8>()?;
+/// Returns 9 or 13 if the stones are on a board of that size, touching its last line, and 19 otherwise
+fn guess_board_size(moves: &[GoMove], handicap_stones: &[BoardCoordinate]) -> u8 {
+    let max_coordinate = moves.iter()
+        .filter_map(|mv| match *mv {
+            GoMove::PlaceStone { coordinate, .. } => Some(coordinate),
+            GoMove::Pass { .. } => None
+        })
+        .chain(handicap_stones.iter().copied())
+        .map(|coordinate| coordinate.x.max(coordinate.y))
+        .max();
+
+    match max_coordinate {
+        Some(8) => 9,
+        Some(12) => 13,
+        _ => 19
+    }
+}
 
-    Ok(Handicap::from(stones))
+/// Returns the player to move after given moves. White starts in handicap games.
+fn next_player(moves: &[GoMove], handicap: Option<Handicap>) -> PlayerColor {
+    match moves.last() {
+        Some(mv) => mv.player().opponent(),
+        None => if handicap.is_some() { PlayerColor::White } else { PlayerColor::Black }
+    }
 }
 
 #[cfg(test)]
@@ -259,18 +432,202 @@ STO 0 3 2 15 16
         assert_eq!(gib.get_komi(), Some(Score::new(6.5)));
         assert_eq!(gib.get_date(), LocalDate::ymd(2020, 3, 13).ok());
         assert_eq!(gib.get_game_place(), Some("Tygem Baduk"));
+        assert_eq!(gib.get_time_control(), Some(TimeControl::new(600, ByoYomi::from(3, 30))));
 
         assert_eq!(gib.get_moves().len(), 2);
         assert_eq!(gib.get_moves()[0], GoMove::place_stone(Black, (16, 3)));
         assert_eq!(gib.get_moves()[1], GoMove::place_stone(White, (15, 16)));
+
+        let metadata = gib.get_metadata();
+        assert_eq!(metadata.black_nick.as_deref(), Some("TheBlack"));
+        assert_eq!(metadata.result, Some(GameResult::Resign(Black)));
+        assert_eq!(metadata.board_size, 19);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let gib = Gib::parse("\\HS\n\\[GAMEDATE=2020- 3-13-23-21-56\\]\n\\[GAMEGONGJE=65\\]\n\\HE\n\\GS\nINI 0 1 2 &4\nSTO 0 2 2 16 3\nSKI 0 3\n\\GE\n").unwrap();
+        let metadata = serde_json::to_value(gib.get_metadata()).unwrap();
+
+        assert_eq!(metadata["date"], "2020-03-13");
+        assert_eq!(metadata["komi"], 6.5);
+        assert_eq!(metadata["handicap"], 2);
+        assert_eq!(serde_json::to_string(&gib.get_moves()).unwrap(),
+                   r#"[{"type":"PlaceStone","player":"White","coordinate":{"x":16,"y":3}},{"type":"Pass","player":"Black"}]"#);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(serde_json::from_str::<GibMetadata>(&json).unwrap(), gib.get_metadata());
+        assert!(serde_json::from_str::<LocalDate>("\"2020-02-30\"").is_err());
+        assert!(serde_json::from_str::<Handicap>("1").is_err());
+    }
+
+    #[test]
+    fn test_passes() {
+        let gib = Gib::parse(r#"\GS
+INI 0 1 0 &4
+STO 0 2 1 16 3
+SKI 0 3
+STO 0 4 1 15 16
+STO 0 5 2 3 3
+SKI 0 6
+SKI 0 7
+\GE
+"#).unwrap();
+
+        assert_eq!(gib.get_moves(), &vec![
+            GoMove::place_stone(Black, (16, 3)),
+            GoMove::Pass { player: White },
+            GoMove::place_stone(Black, (15, 16)),
+            GoMove::place_stone(White, (3, 3)),
+            GoMove::Pass { player: Black },
+            GoMove::Pass { player: White },
+        ]);
+    }
+
+    #[test]
+    fn test_pass_in_handicap_game() {
+        let gib = Gib::parse(r#"\GS
+INI 0 1 2 &4
+SKI 0 1
+STO 0 2 1 16 3
+\GE
+"#).unwrap();
+
+        assert_eq!(gib.get_moves(), &vec![
+            GoMove::Pass { player: White },
+            GoMove::place_stone(Black, (16, 3)),
+        ]);
+    }
+
+    #[test]
+    fn test_time_left_parsing() {
+        let gib = Gib::parse(r#"\GS
+STO 0 2 1 16 3 587 3
+STO 0 3 2 15 16 25 2
+STO 0 4 1 3 3 542
+STO 0 5 2 3 15
+STO 0 6 1 4 4 530 3 0 &1
+STO 0 7 2 5 5 &1
+\GE
+"#).unwrap();
+
+        assert_eq!(gib.get_time_left(0), Some(TimeLeft::new(587, Some(3))));
+        assert_eq!(gib.get_time_left(1), Some(TimeLeft::new(25, Some(2))));
+        assert_eq!(gib.get_time_left(2), Some(TimeLeft::new(542, None)));
+        assert_eq!(gib.get_time_left(3), None);
+        assert_eq!(gib.get_time_left(4), Some(TimeLeft::new(530, Some(3))));
+        assert_eq!(gib.get_time_left(5), None);
+        assert_eq!(gib.get_time_left(6), None);
+        assert_eq!(gib.get_moves()[1], GoMove::place_stone(White, (15, 16)));
+    }
+
+    #[test]
+    fn test_fixed_handicap() {
+        let gib = Gib::parse(r#"\GS
+INI 0 1 2 &4
+STO 0 1 2 16 3
+STO 0 2 1 15 16
+\GE
+"#).unwrap();
+
+        assert_eq!(gib.get_handicap(), Handicap::from(2));
+        assert_eq!(gib.get_handicap_points().unwrap(), vec![BoardCoordinate::new(3, 15), BoardCoordinate::new(15, 3)]);
+        assert_eq!(gib.get_moves().len(), 2);
+    }
+
+    #[test]
+    fn test_free_handicap() {
+        let gib = Gib::parse(r#"\GS
+INI 0 1 3 &4
+STO 0 1 1 3 3
+STO 0 2 1 15 15
+STO 0 3 1 9 9
+STO 0 4 2 16 3
+SKI 0 5
+STO 0 6 2 15 16
+\GE
+"#).unwrap();
+
+        assert_eq!(gib.get_handicap(), Handicap::from(3));
+        assert_eq!(gib.get_handicap_points().unwrap(), vec![BoardCoordinate::new(3, 3), BoardCoordinate::new(15, 15), BoardCoordinate::new(9, 9)]);
+        assert_eq!(gib.get_moves(), &vec![
+            GoMove::place_stone(White, (16, 3)),
+            GoMove::Pass { player: Black },
+            GoMove::place_stone(White, (15, 16)),
+        ]);
+    }
+
+    #[test]
+    fn test_board_size() {
+        fn board_size(gib: &str) -> u8 {
+            Gib::parse(gib).unwrap().get_board_size()
+        }
+
+        assert_eq!(board_size(""), 19);
+        assert_eq!(board_size("STO 0 2 1 2 6\nSTO 0 3 2 8 0"), 9);
+        assert_eq!(board_size("STO 0 2 1 2 6\nSTO 0 3 2 12 3"), 13);
+        assert_eq!(board_size("STO 0 2 1 2 6\nSTO 0 3 2 6 2"), 19);
+        assert_eq!(board_size("STO 0 2 1 2 6\nSTO 0 3 2 15 3"), 19);
+        assert_eq!(board_size("\\[GAMEBOARDSIZE=13\\]\nSTO 0 2 1 2 6"), 13);
+        assert_eq!(board_size("\\[GAMEBOARDSIZE=9\\]\nSTO 0 2 1 2 6"), 9);
+        assert_eq!(board_size("\\[GAMEBOARDSIZE=foo\\]\nSTO 0 2 1 2 6"), 19);
     }
 
     #[test]
     fn test_date_parsing() {
-        assert_eq!(parse_gib_date("2020-10-13-23-21-56"), LocalDate::ymd(2020, 10, 13));
-        assert_eq!(parse_gib_date("2020-10- 5-23-21-56"), LocalDate::ymd(2020, 10, 5));
-        assert_eq!(parse_gib_date("2020- 3-13-23-21-56"), LocalDate::ymd(2020, 3, 13));
-        assert_eq!(parse_gib_date("2021- 3- 4- 3- 1- 6"), LocalDate::ymd(2021, 3, 4));
+        assert_eq!(parse_gib_date("2020-10-13-23-21-56").ok(), LocalDate::ymd(2020, 10, 13).ok());
+        assert_eq!(parse_gib_date("2020-10- 5-23-21-56").ok(), LocalDate::ymd(2020, 10, 5).ok());
+        assert_eq!(parse_gib_date("2020- 3-13-23-21-56").ok(), LocalDate::ymd(2020, 3, 13).ok());
+        assert_eq!(parse_gib_date("2021- 3- 4- 3- 1- 6").ok(), LocalDate::ymd(2021, 3, 4).ok());
+
+        let date = LocalDate::ymd(2021, 3, 4).unwrap();
+        assert_eq!(parse_gib_date_time("2021- 3- 4- 3- 1- 6"), LocalDateTime::new(date, 3, 1, 6).ok());
+        assert_eq!(parse_gib_date_time("2021- 3- 4"), None);
+        assert_eq!(parse_gib_date_time("2021- 3- 4-25- 1- 6"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        fn parse_error(gib: &str) -> (usize, usize, GibParseErrorKind) {
+            let e = Gib::parse(gib).unwrap_err();
+            (e.line, e.column, e.kind)
+        }
+
+        assert_eq!(parse_error("\\HS\n\\[GAMEDATE\\]\n\\HE"), (2, 1, GibParseErrorKind::InvalidMetadataLine));
+        assert_eq!(parse_error("\\GS\nSTO 0 2 1 16 3\nSTO 0 3 3 15 16"), (3, 9, GibParseErrorKind::InvalidPlayer("3".to_string())));
+        assert_eq!(parse_error("\\GS\nSTO 0 2 1 16 3\nSTO 0 3 2 15 x"),
+                   (3, 14, GibParseErrorKind::InvalidMove("expected number".to_string())));
+        assert_eq!(parse_error("\\GS\nSTO 0 2 1 16 3\nSTO 0 3 2 15 999"),
+                   (3, 14, GibParseErrorKind::InvalidMove("not a number '999'".to_string())));
+        assert_eq!(parse_error("\\GS\nINI 0 1 &4"), (2, 9, GibParseErrorKind::InvalidHandicap("expected number".to_string())));
+        assert_eq!(parse_error("\\[GAMEBOARDSIZE=5\\]\n\\GS\nINI 0 1 2 &4"),
+                   (3, 9, GibParseErrorKind::InvalidHandicap("can't place 2 handicap stones on 5x5 board".to_string())));
+
+        let e = Gib::parse("\\GS\nSTO 0 3 3 15 16").unwrap_err();
+        assert_eq!(e.to_string(), "line 2, column 9: invalid player '3' in 'STO 0 3 3 15 16'");
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        fn warnings(gib: &str) -> Vec<(usize, usize, GibParseErrorKind)> {
+            Gib::parse(gib).unwrap().get_warnings().iter().map(|e| (e.line, e.column, e.kind.clone())).collect()
+        }
+
+        assert_eq!(warnings("\\[GAMEDATE=2020-13-01-23-21-56\\]"),
+                   vec![(1, 12, GibParseErrorKind::InvalidDate("invalid date 2020-13-01".to_string()))]);
+        assert_eq!(warnings("\\[GAMEDATE=2020-x3-01-23-21-56\\]"),
+                   vec![(1, 17, GibParseErrorKind::InvalidDate("expected number".to_string()))]);
+        assert_eq!(warnings("\\HS\n\\HE\n\\XS\n\\XE"), vec![
+            (3, 1, GibParseErrorKind::UnknownSection("XS".to_string())),
+            (4, 1, GibParseErrorKind::UnknownSection("XE".to_string())),
+        ]);
+        assert!(warnings("\\[GAMEDATE=2020- 3-13-23-21-56\\]").is_empty());
+
+        let gib = Gib::parse("\\[GAMEDATE=2020-13-01-23-21-56\\]\n\\GS\nSTO 0 2 1 16 3\n\\GE").unwrap();
+        assert_eq!(gib.get_date(), None);
+        assert_eq!(gib.get_date_time(), None);
+        assert_eq!(gib.get_moves().len(), 1);
     }
 
     #[test]
@@ -280,6 +637,27 @@ STO 0 3 2 15 16
         assert_eq!(GameResult::from_gib("GBKIND:3,GTYPE:0,GCDT:0,GTIME:600-30-3,GRLT:7,ZIPSU:0,DUM:0,GONGJE:65,TCNT:185,AUSZ:0"), Some(GameResult::Time(Black)));
     }
 
+    #[test]
+    fn test_time_control_parsing() {
+        assert_eq!(TimeControl::from_gib("600-30-3"), Some(TimeControl::new(600, ByoYomi::from(3, 30))));
+        assert_eq!(TimeControl::from_gib("1200-60-5"), Some(TimeControl::new(1200, ByoYomi::from(5, 60))));
+        assert_eq!(TimeControl::from_gib("300-0-0"), Some(TimeControl::new(300, None)));
+        assert_eq!(TimeControl::from_gib("1800"), Some(TimeControl::new(1800, None)));
+        assert_eq!(TimeControl::from_gib("600-10-0"), Some(TimeControl::with_increment(600, 10)));
+        assert_eq!(TimeControl::from_gib(""), None);
+        assert_eq!(TimeControl::from_gib("600F"), None);
+
+        // Only the main time is known for other variants
+        assert_eq!(TimeControl::parse_gib("600-30-3"), Some((TimeControl::new(600, ByoYomi::from(3, 30)), true)));
+        assert_eq!(TimeControl::parse_gib("600-30"), Some((TimeControl::new(600, None), false)));
+        assert_eq!(TimeControl::parse_gib("600-0-3"), Some((TimeControl::new(600, None), false)));
+        assert_eq!(TimeControl::parse_gib("600-30-3-10"), Some((TimeControl::new(600, None), false)));
+
+        for gtime in ["600-30-3", "600-10-0", "300-0-0"].iter() {
+            assert_eq!(TimeControl::from_gib(gtime).unwrap().to_gib(), *gtime);
+        }
+    }
+
     #[test]
     fn test_parse_name() {
         assert_eq!(parse_gib_name(""), (None, None));
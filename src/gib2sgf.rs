@@ -1,18 +1,162 @@
-use crate::gib::{Gib, GibParseError};
-use crate::sgf::{SgfCollection, SgfTree, SgfNode};
-use crate::go::{PlayerColor, GoMove};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::gib::Gib;
+use crate::gib_document::GibParseError;
+use crate::sgf::{SgfCollection, SgfTree, SgfNode, ToSgf};
+use crate::go::{PlayerColor, GoMove, Board, BoardCoordinate, IllegalMove, BOARD_SIZES};
+use crate::encoding::decode_gib;
 use GoMove::{PlaceStone, Pass};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Offset of the times in GIB files from UTC in minutes. Tygem is a Korean server.
+const TYGEM_UTC_OFFSET: i32 = 9 * 60;
+
+/// Largest offset of a timezone from UTC in minutes
+const MAX_UTC_OFFSET: i32 = 14 * 60;
+
+/// How to react to moves that can't be played on the board, which
+/// usually means that the GIB file is truncated or garbled.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Validation {
+    /// Don't replay the moves at all
+    Skip,
+    /// Convert the file anyway, but report the illegal moves
+    Warn,
+    /// Fail the conversion on the first illegal move
+    Fail,
+}
+
+/// Result of a validated conversion
+#[derive(Debug)]
+pub struct Conversion {
+    pub sgf: String,
+    /// Illegal moves found when validating with `Validation::Warn`
+    pub warnings: Vec<IllegalMove>,
+    /// Problems in the GIB file that were ignored, eg. an invalid date
+    pub parse_warnings: Vec<GibParseError>,
+}
+
+/// Settings for the generated SGF file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConversionOptions {
+    pub rules: Rules,
+    /// Board size to use instead of the one from the GIB file, between 2 and 25
+    pub board_size: Option<u8>,
+    /// Whether to write the `AP` property with the version of this library
+    pub include_application: bool,
+    /// Additional root properties, eg. `EV` or `GN`. These override the properties
+    /// from the GIB file with the same name.
+    pub extra_properties: Vec<(String, String)>,
+    pub date_format: DateFormat,
+    /// Timezone for the date, as minutes from UTC, at most 14 hours. By default the Korean
+    /// time of Tygem is kept.
+    pub utc_offset: Option<i32>,
+    pub validation: Validation,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Rules {
+    Japanese,
+    Korean,
+    Chinese,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DateFormat {
+    /// `DT[2020-03-13]`, as specified by SGF
+    Date,
+    /// `DT[2020-03-13 23:21:56]`, which is not standard, but understood by many programs
+    DateTime,
+    /// Don't write the date at all
+    Omit,
+}
+
+#[derive(Debug)]
+pub enum ConversionError {
+    Parse(GibParseError),
+    IllegalMove(IllegalMove),
+    /// The fixed handicap placement doesn't fit on the board size given in the options
+    InvalidHandicap(String),
+    /// The board size or UTC offset of the options is out of range
+    InvalidOptions(String),
+}
+
 ///
 /// Converts a GIB file to SGF. Both the input and output are represented
 /// as in-memory contents of the files.
 ///
 pub fn gib_to_sgf(gib_data: &str) -> Result<String, GibParseError> {
     let gib = Gib::parse(gib_data)?;
+    // Parsing checks that the handicap fits on the board size of the file
+    let handicap_points = gib.get_handicap_points().unwrap_or_default();
+    Ok(convert(&gib, &ConversionOptions::default(), &handicap_points))
+}
+
+///
+/// Converts a GIB file in any of the encodings used by Tygem clients to SGF.
+/// The resulting SGF is always UTF-8.
+///
+pub fn gib_to_sgf_bytes(gib_data: &[u8]) -> Result<String, GibParseError> {
+    gib_to_sgf(&decode_gib(gib_data).0)
+}
+
+///
+/// Converts a GIB file to SGF like `gib_to_sgf`, but first replays the
+/// moves on a board to check that they make sense.
+///
+pub fn gib_to_sgf_validated(gib_data: &str, validation: Validation) -> Result<Conversion, ConversionError> {
+    gib_to_sgf_with_options(gib_data, &ConversionOptions { validation, ..ConversionOptions::default() })
+}
+
+///
+/// Converts a GIB file to SGF using given options instead of the defaults.
+///
+pub fn gib_to_sgf_with_options(gib_data: &str, options: &ConversionOptions) -> Result<Conversion, ConversionError> {
+    options.validate().map_err(ConversionError::InvalidOptions)?;
+    let gib = Gib::parse(gib_data).map_err(ConversionError::Parse)?;
+    let board_size = options.board_size.unwrap_or_else(|| gib.get_board_size());
+    let handicap_points = gib.get_handicap_points_on(board_size).map_err(ConversionError::InvalidHandicap)?;
+
+    let warnings = match options.validation {
+        Validation::Skip => Vec::new(),
+        Validation::Warn => replay(&gib, board_size, &handicap_points),
+        Validation::Fail => match replay(&gib, board_size, &handicap_points).into_iter().next() {
+            Some(illegal_move) => return Err(ConversionError::IllegalMove(illegal_move)),
+            None => Vec::new()
+        }
+    };
+
+    let parse_warnings = gib.get_warnings().to_vec();
+    Ok(Conversion { sgf: convert(&gib, options, &handicap_points), warnings, parse_warnings })
+}
 
+/// Plays the handicap stones and all moves on a board, returning the moves that
+/// were illegal. Illegal moves are skipped so that the rest of the game can be checked.
+fn replay(gib: &Gib, board_size: u8, handicap_points: &[BoardCoordinate]) -> Vec<IllegalMove> {
+    let mut board = Board::new(board_size);
+    let mut illegal_moves = Vec::new();
+
+    for &point in handicap_points {
+        if let Err(e) = board.add_stone(PlayerColor::Black, point) {
+            illegal_moves.push(e);
+        }
+    }
+
+    for (index, mv) in gib.get_moves().iter().enumerate() {
+        if let Err(e) = board.play(mv) {
+            illegal_moves.push(IllegalMove { move_number: index + 1, ..e });
+        }
+    }
+
+    illegal_moves
+}
+
+fn convert(gib: &Gib, options: &ConversionOptions, handicap_points: &[BoardCoordinate]) -> String {
     let mut root = SgfNode::new();
+    let board_size = options.board_size.unwrap_or_else(|| gib.get_board_size());
 
     // https://www.red-bean.com/sgf/properties.html
     root.set_property_maybe("PB", gib.get_nick(PlayerColor::Black));
@@ -20,36 +164,313 @@ pub fn gib_to_sgf(gib_data: &str) -> Result<String, GibParseError> {
     root.set_property_maybe("PW", gib.get_nick(PlayerColor::White));
     root.set_property_maybe("WR", gib.get_rank(PlayerColor::White));
     root.set_property_maybe("KM", gib.get_komi());
-    root.set_property_maybe("DT", gib.get_date());
+
+    // A time that can't be moved to the timezone is left out rather than written in the wrong one
+    let date_time = gib.get_date_time().map(|time| match options.utc_offset {
+        Some(offset) => time.plus_minutes(offset - TYGEM_UTC_OFFSET),
+        None => Some(time)
+    });
+    let date = match date_time {
+        Some(time) => time.map(|t| t.date()),
+        None => gib.get_date(),
+    };
+    match options.date_format {
+        DateFormat::Date => root.set_property_maybe("DT", date),
+        DateFormat::DateTime => match date_time.flatten() {
+            Some(time) => root.set_property("DT", time),
+            None => root.set_property_maybe("DT", date),
+        }
+        DateFormat::Omit => {}
+    }
+
     root.set_property_maybe("RE", gib.get_result());
     root.set_property_maybe("SO", gib.get_game_place());
 
-    root.set_property("RU", "Japanese"); // Assume files are from Tygem which uses Japanese rules
-    root.set_property("SZ", "19"); // Assume board size
+    if let Some(time_control) = gib.get_time_control() {
+        root.set_property("TM", time_control.main_time());
+        // Settings that can't be interpreted are kept as they are, so they are not lost
+        match (gib.get_unknown_time_settings(), time_control.increment()) {
+            (Some(gtime), _) => root.set_property("OT", format!("GTIME {}", gtime)),
+            (None, Some(increment)) => root.set_property("OT", format!("Fischer +{}s", increment)),
+            (None, None) => root.set_property_maybe("OT", time_control.byo_yomi()),
+        }
+    }
+
+    root.set_property("RU", options.rules);
+    root.set_property("SZ", board_size);
 
     // General metadata
     root.set_property("GM", "1"); // Game, 1 = Go
     root.set_property("FF", "4"); // File format version
     root.set_property("CA", "UTF-8"); // Charset
-    root.set_property("AP", format!("gib2sgf:{}", VERSION).as_str()); // Application that generated the file
+    if options.include_application {
+        root.set_property("AP", format!("gib2sgf:{}", VERSION).as_str()); // Application that generated the file
+    }
 
     if let Some(handicap) = gib.get_handicap() {
         root.set_property("HA", handicap);
-        root.set_property_list("AB", handicap.handicap_points())
+        root.set_property_list("AB", handicap_points.to_vec())
+    }
+
+    for (name, value) in &options.extra_properties {
+        root.set_property(name, value.as_str());
     }
 
     let mut game = SgfTree::new();
     game.add_node(root);
-    for mv in gib.get_moves() {
-        match *mv {
+    for (index, mv) in gib.get_moves().iter().enumerate() {
+        let node = match *mv {
             PlaceStone { player, coordinate } => game.add_move(player, coordinate),
-            Pass { .. } => {
-                // TODO: support passes
-            }
+            Pass { player } => game.add_pass(player),
+        };
+
+        if let Some(time_left) = gib.get_time_left(index) {
+            node.set_time_left(mv.player(), time_left);
         }
     }
 
     let sgf = SgfCollection::from_game(game);
 
-    Ok(sgf.to_sgf())
+    sgf.to_sgf()
+}
+
+impl Default for ConversionOptions {
+    fn default() -> ConversionOptions {
+        ConversionOptions {
+            rules: Rules::Japanese, // Assume files are from Tygem which uses Japanese rules
+            board_size: None,
+            include_application: true,
+            extra_properties: Vec::new(),
+            date_format: DateFormat::Date,
+            utc_offset: None,
+            validation: Validation::Skip,
+        }
+    }
+}
+
+/// Game info properties of SGF, the only ones that can be added to the root node.
+/// Properties like `SZ`, `HA` or `AB` would change the game itself.
+const GAME_INFO_PROPERTIES: &[&str] = &["AN", "BR", "BT", "CP", "DT", "EV", "GC", "GN", "KM", "ON", "OT", "PB",
+    "PC", "PW", "RE", "RO", "RU", "SO", "TM", "US", "WR", "WT"];
+
+impl ConversionOptions {
+    /// Adds a root property, checking that the name is one of the SGF game info properties
+    pub fn add_property(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err(format!("invalid property name '{}'", name));
+        }
+        if !GAME_INFO_PROPERTIES.contains(&name) {
+            return Err(format!("property '{}' is not a game info property", name));
+        }
+
+        self.extra_properties.retain(|(n, _)| n != name);
+        self.extra_properties.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// Sets the board size, checking that it is between 2 and 25
+    pub fn set_board_size(&mut self, board_size: u8) -> Result<(), String> {
+        self.board_size = Some(check_board_size(board_size)?);
+        Ok(())
+    }
+
+    /// Sets the timezone as minutes from UTC, checking that it is at most 14 hours
+    pub fn set_utc_offset(&mut self, minutes: i32) -> Result<(), String> {
+        self.utc_offset = Some(check_utc_offset(minutes)?);
+        Ok(())
+    }
+
+    /// Checks the fields that can also be assigned without the setters
+    fn validate(&self) -> Result<(), String> {
+        self.board_size.map(check_board_size).transpose()?;
+        self.utc_offset.map(check_utc_offset).transpose()?;
+        Ok(())
+    }
+}
+
+fn check_board_size(board_size: u8) -> Result<u8, String> {
+    if BOARD_SIZES.contains(&board_size) {
+        Ok(board_size)
+    } else {
+        Err(format!("invalid board size {}", board_size))
+    }
+}
+
+fn check_utc_offset(minutes: i32) -> Result<i32, String> {
+    if (-MAX_UTC_OFFSET..=MAX_UTC_OFFSET).contains(&minutes) {
+        Ok(minutes)
+    } else {
+        Err(format!("invalid UTC offset of {} minutes", minutes))
+    }
+}
+
+impl ToSgf for Rules {
+    fn to_sgf(&self) -> String {
+        match self {
+            Rules::Japanese => "Japanese",
+            Rules::Korean => "Korean",
+            Rules::Chinese => "Chinese",
+        }.to_string()
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Rules, String> {
+        match str.to_ascii_lowercase().as_str() {
+            "japanese" => Ok(Rules::Japanese),
+            "korean" => Ok(Rules::Korean),
+            "chinese" => Ok(Rules::Chinese),
+            _ => Err(format!("unknown rules '{}', expected japanese, korean or chinese", str)),
+        }
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<DateFormat, String> {
+        match str.to_ascii_lowercase().as_str() {
+            "date" => Ok(DateFormat::Date),
+            "datetime" => Ok(DateFormat::DateTime),
+            "none" => Ok(DateFormat::Omit),
+            _ => Err(format!("unknown date format '{}', expected date, datetime or none", str)),
+        }
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::Parse(e) => write!(f, "{}", e),
+            ConversionError::IllegalMove(e) => write!(f, "{}", e),
+            ConversionError::InvalidHandicap(e) => write!(f, "{}", e),
+            ConversionError::InvalidOptions(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::go::{IllegalMoveReason, BoardCoordinate};
+
+    const GARBLED_GIB: &str = r#"\HS
+\[GAMEBLACKNAME=TheBlack (2D)\]
+\HE
+\GS
+INI 0 1 2 &4
+STO 0 1 2 16 3
+STO 0 2 1 15 3
+STO 0 3 2 16 3
+STO 0 4 1 25 3
+\GE
+"#;
+
+    #[test]
+    fn test_legacy_encoding() {
+        let gib = "\\[GAMEBLACKNAME=흑돌 (2D)\\]\n\\[GAMEPLACE=타이젬\\]\n";
+        let (cp949, _, _) = encoding_rs::EUC_KR.encode(gib);
+
+        let sgf = gib_to_sgf_bytes(&cp949).unwrap();
+        assert!(sgf.starts_with("(;PB[흑돌]BR[2D]SO[타이젬]"));
+        assert!(sgf.contains("CA[UTF-8]"));
+        assert_eq!(sgf, gib_to_sgf(gib).unwrap());
+    }
+
+    #[test]
+    fn test_validation() {
+        let occupied_handicap = IllegalMove { move_number: 2, reason: IllegalMoveReason::Occupied(BoardCoordinate::new(15, 3)) };
+        let occupied = IllegalMove { move_number: 3, reason: IllegalMoveReason::Occupied(BoardCoordinate::new(16, 3)) };
+        let off_board = IllegalMove { move_number: 4, reason: IllegalMoveReason::OffBoard(BoardCoordinate::new(25, 3)) };
+
+        let conversion = gib_to_sgf_validated(GARBLED_GIB, Validation::Warn).unwrap();
+        assert_eq!(conversion.warnings, vec![occupied_handicap, occupied, off_board]);
+        assert_eq!(conversion.sgf, gib_to_sgf(GARBLED_GIB).unwrap());
+
+        match gib_to_sgf_validated(GARBLED_GIB, Validation::Fail) {
+            Err(ConversionError::IllegalMove(e)) => assert_eq!(e, occupied_handicap),
+            _ => panic!("expected illegal move")
+        }
+
+        assert!(gib_to_sgf_validated(GARBLED_GIB, Validation::Skip).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_options() {
+        let gib = "\\[GAMEDATE=2020-12-31-23-21-56\\]\n\\[GAMEPLACE=Tygem Baduk\\]\n\\GS\nINI 0 1 2 &4\nSTO 0 2 2 3 3\n\\GE\n";
+        let default = gib_to_sgf(gib).unwrap();
+        assert!(default.contains("DT[2020-12-31]"));
+        assert!(default.contains("RU[Japanese]SZ[19]"));
+        assert!(default.contains("AB[dp][pd]"));
+        assert!(default.contains("AP[gib2sgf:"));
+
+        let mut options = ConversionOptions {
+            rules: Rules::Korean,
+            board_size: Some(9),
+            include_application: false,
+            date_format: DateFormat::DateTime,
+            utc_offset: Some(0),
+            ..ConversionOptions::default()
+        };
+        options.add_property("EV", "Club [league]").unwrap();
+        options.add_property("SO", "Somewhere else").unwrap();
+        assert!(options.add_property("ev", "foo").is_err());
+        for name in &["SZ", "AB", "AW", "B", "W", "HA", "FF", "XX"] {
+            assert_eq!(options.add_property(name, "foo"), Err(format!("property '{}' is not a game info property", name)));
+        }
+
+        let sgf = gib_to_sgf_with_options(gib, &options).unwrap().sgf;
+        assert!(sgf.contains("DT[2020-12-31 14:21:56]"));
+        assert!(sgf.contains("SO[Somewhere else]"));
+        assert!(sgf.contains("RU[Korean]SZ[9]"));
+        assert!(sgf.contains("AB[cg][gc]"));
+        assert!(sgf.contains("EV[Club [league\\]]"));
+        assert!(!sgf.contains("AP["));
+
+        options.date_format = DateFormat::Omit;
+        assert!(!gib_to_sgf_with_options(gib, &options).unwrap().sgf.contains("DT["));
+
+        options.board_size = Some(5);
+        match gib_to_sgf_with_options(gib, &options) {
+            Err(ConversionError::InvalidHandicap(e)) => assert_eq!(e, "can't place 2 handicap stones on 5x5 board"),
+            _ => panic!("expected invalid handicap")
+        }
+
+        assert_eq!("chinese".parse::<Rules>(), Ok(Rules::Chinese));
+        assert_eq!("none".parse::<DateFormat>(), Ok(DateFormat::Omit));
+        assert!("iso".parse::<DateFormat>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let gib = "\\GS\nINI 0 1 4 &4\nSTO 0 2 2 3 3\n\\GE\n";
+        let mut options = ConversionOptions { validation: Validation::Warn, ..ConversionOptions::default() };
+
+        for &size in [0, 1, 26, 255].iter() {
+            assert_eq!(options.set_board_size(size), Err(format!("invalid board size {}", size)));
+            assert_eq!(options.board_size, None);
+            let invalid = ConversionOptions { board_size: Some(size), ..options.clone() };
+            assert!(matches!(gib_to_sgf_with_options(gib, &invalid), Err(ConversionError::InvalidOptions(_))));
+        }
+        for &minutes in [i32::MIN, -14 * 60 - 1, 14 * 60 + 1, i32::MAX].iter() {
+            assert!(options.set_utc_offset(minutes).is_err());
+            let invalid = ConversionOptions { utc_offset: Some(minutes), ..options.clone() };
+            assert!(matches!(gib_to_sgf_with_options(gib, &invalid), Err(ConversionError::InvalidOptions(_))));
+        }
+        assert_eq!(options.set_utc_offset(-14 * 60), Ok(()));
+    }
+
+    #[test]
+    fn test_date_out_of_range() {
+        for &(date, utc_offset) in [("0- 1- 1- 1- 0- 0", 0), ("65535-12-31-23- 0- 0", 14 * 60)].iter() {
+            let options = ConversionOptions { utc_offset: Some(utc_offset), ..ConversionOptions::default() };
+            let gib = format!("\\[GAMEDATE={}\\]\n\\GS\nINI 0 1 0 &4\n\\GE\n", date);
+            let sgf = gib_to_sgf_with_options(&gib, &options).unwrap().sgf;
+            assert!(!sgf.contains("DT["), "{}", sgf);
+        }
+    }
 }
@@ -0,0 +1,405 @@
+//! Lossless representation of the line structure of GIB files.
+//!
+//! A GIB file consists of a header section between `\HS` and `\HE` lines and a game section
+//! between `\GS` and `\GE` lines. `GibDocument` keeps every line in its original order along
+//! with what we know about it, so that lines we don't yet understand can still be inspected
+//! and the original file can be reproduced.
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::go::{PlayerColor, BoardCoordinate, TimeLeft};
+use crate::lexer::{Lexer, LexerError};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GibDocument {
+    entries: Vec<GibEntry>,
+    /// Lines that could not be parsed, in the order of the file
+    errors: Vec<GibParseError>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GibEntry {
+    Section(GibSection),
+    /// Line outside of any section
+    Line(GibLine),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GibSection {
+    pub kind: GibSectionKind,
+    /// Lines between the start and end markers
+    pub lines: Vec<GibLine>,
+    /// False if the file ended before the end marker
+    pub closed: bool,
+    start: GibLine,
+    end: Option<GibLine>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GibSectionKind {
+    Header,
+    Game,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GibLine {
+    /// 1-based line number in the original file
+    pub number: usize,
+    /// Contents of the line without the line ending
+    pub text: String,
+    /// Line ending of this line, `\r\n`, `\n`, or empty for the last line of a file without one
+    pub ending: &'static str,
+    pub record: GibRecord,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GibRecord {
+    /// Section start or end marker, eg. `\HS` or `\GE`
+    SectionMarker,
+    /// `\[key=value\]`
+    Attribute { key: String, value: String },
+    /// `INI <num> <num> <handicap> ...`
+    Init { handicap: u8 },
+    /// `STO <num> <move number> <player> <x> <y> [<seconds left> [<periods left>]]`
+    Stone { move_number: u16, player: PlayerColor, coordinate: BoardCoordinate, time_left: Option<TimeLeft> },
+    /// `SKI ...`, a pass
+    Skip,
+    /// Any line we don't know the meaning of, like `2 1 0` or `186 0 &4`, or a line
+    /// that could not be parsed
+    Unknown,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GibParseError {
+    pub kind: GibParseErrorKind,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    /// Contents of the offending line
+    pub line_text: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GibParseErrorKind {
+    InvalidMetadataLine,
+    InvalidMove(String),
+    InvalidHandicap(String),
+    InvalidPlayer(String),
+    InvalidDate(String),
+    UnknownSection(String),
+}
+
+/// Error within a single line, positioned by byte offset in the line
+#[derive(Debug)]
+pub(crate) struct LineError {
+    pub pos: usize,
+    pub kind: GibParseErrorKind,
+}
+
+pub(crate) type LineResult<T> = Result<T, LineError>;
+
+impl GibDocument {
+    /// Splits the file into lines and sections. Lines that can't be parsed are kept as
+    /// unknown lines, and their errors are available from `errors`.
+    pub fn parse(str: &str) -> GibDocument {
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        let mut current: Option<GibSection> = None;
+
+        for (index, raw_line) in str.split_inclusive('\n').enumerate() {
+            let number = index + 1;
+            let (text, ending) = split_line_ending(raw_line);
+            let record = parse_record(text).unwrap_or_else(|e| {
+                errors.push(e.at(number, text));
+                GibRecord::Unknown
+            });
+            let line = GibLine { number, text: text.to_string(), ending, record };
+
+            if let Some(kind) = GibSectionKind::started_by(text) {
+                if let Some(section) = current.take() {
+                    entries.push(GibEntry::Section(section));
+                }
+                current = Some(GibSection { kind, lines: Vec::new(), closed: false, start: line, end: None });
+            } else if let Some(section) = current.as_mut().filter(|s| s.kind.end_marker() == text) {
+                section.closed = true;
+                section.end = Some(line);
+                entries.push(GibEntry::Section(current.take().unwrap()));
+            } else if let Some(section) = current.as_mut() {
+                section.lines.push(line);
+            } else {
+                entries.push(GibEntry::Line(line));
+            }
+        }
+
+        if let Some(section) = current {
+            entries.push(GibEntry::Section(section));
+        }
+
+        GibDocument { entries, errors }
+    }
+
+    pub fn entries(&self) -> &Vec<GibEntry> {
+        &self.entries
+    }
+
+    pub fn errors(&self) -> &[GibParseError] {
+        &self.errors
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item=&GibSection> {
+        self.entries.iter().filter_map(|e| match e {
+            GibEntry::Section(section) => Some(section),
+            GibEntry::Line(_) => None
+        })
+    }
+
+    pub fn section(&self, kind: GibSectionKind) -> Option<&GibSection> {
+        self.sections().find(|s| s.kind == kind)
+    }
+
+    /// Iterates over all lines of the file in order, including section markers
+    pub fn lines(&self) -> impl Iterator<Item=&GibLine> {
+        self.entries.iter().flat_map(|e| -> Box<dyn Iterator<Item=&GibLine> + '_> {
+            match e {
+                GibEntry::Section(section) =>
+                    Box::new(std::iter::once(&section.start).chain(section.lines.iter()).chain(section.end.iter())),
+                GibEntry::Line(line) => Box::new(std::iter::once(line)),
+            }
+        })
+    }
+
+    /// Reproduces the original file exactly
+    pub fn to_gib(&self) -> String {
+        self.lines().flat_map(|l| [l.text.as_str(), l.ending]).collect()
+    }
+}
+
+impl GibSectionKind {
+    fn started_by(line: &str) -> Option<GibSectionKind> {
+        match line {
+            r"\HS" => Some(GibSectionKind::Header),
+            r"\GS" => Some(GibSectionKind::Game),
+            _ => None
+        }
+    }
+
+    fn end_marker(&self) -> &'static str {
+        match self {
+            GibSectionKind::Header => r"\HE",
+            GibSectionKind::Game => r"\GE",
+        }
+    }
+}
+
+fn split_line_ending(line: &str) -> (&str, &'static str) {
+    if let Some(text) = line.strip_suffix("\r\n") {
+        (text, "\r\n")
+    } else if let Some(text) = line.strip_suffix('\n') {
+        (text, "\n")
+    } else {
+        (line, "")
+    }
+}
+
+fn parse_record(line: &str) -> LineResult<GibRecord> {
+    if line.starts_with(r"\[") {
+        let (key, value) = parse_metadata_line(line)?;
+        Ok(GibRecord::Attribute { key: key.to_string(), value: value.to_string() })
+    } else if line.starts_with("STO ") {
+        parse_place_stone(line)
+    } else if line.starts_with("SKI") {
+        Ok(GibRecord::Skip)
+    } else if line.starts_with("INI ") {
+        parse_handicap_line(line)
+    } else if line == r"\HS" || line == r"\HE" || line == r"\GS" || line == r"\GE" {
+        Ok(GibRecord::SectionMarker)
+    } else {
+        Ok(GibRecord::Unknown)
+    }
+}
+
+impl PlayerColor {
+    fn from_gib(s: &str) -> Option<PlayerColor> {
+        match s {
+            "1" => Some(PlayerColor::Black),
+            "2" => Some(PlayerColor::White),
+            _ => None
+        }
+    }
+}
+
+/// Extract key and value from line of form `\[key=value\]`
+fn parse_metadata_line(str: &str) -> LineResult<(&str, &str)> {
+    let trimmed = str.trim();
+    if trimmed.starts_with(r"\[") && trimmed.ends_with(r"\]") {
+        let contents = &trimmed[2..trimmed.len() - 2];
+        if let Some(index) = contents.find('=') {
+            let name = &contents[..index];
+            let value = &contents[index + 1..];
+            return Ok((name, value));
+        }
+    }
+    Err(LineError { pos: 0, kind: GibParseErrorKind::InvalidMetadataLine })
+}
+
+/// Extract move from line of form `STO <num> <num> <player> <x> <y> [<seconds left> [<periods left>]]`
+///
+/// Only some clients write the trailing clock fields, and unknown fields after them are ignored.
+fn parse_place_stone(str: &str) -> LineResult<GibRecord> {
+    let invalid_move = LineError::from_lexer(GibParseErrorKind::InvalidMove);
+    let mut lexer = Lexer::new(str.trim_end());
+    lexer.expect("STO ").map_err(invalid_move)?;
+
+    lexer.read_number::<i16>().map_err(invalid_move)?;
+    lexer.expect(" ").map_err(invalid_move)?;
+    let move_number = lexer.read_number::<u16>().map_err(invalid_move)?;
+    lexer.expect(" ").map_err(invalid_move)?;
+    let player_pos = lexer.pos();
+    let player_str = lexer.read_while(|c| c != ' ');
+    let player = PlayerColor::from_gib(player_str)
+        .ok_or_else(|| LineError { pos: player_pos, kind: GibParseErrorKind::InvalidPlayer(player_str.to_string()) })?;
+    lexer.expect(" ").map_err(invalid_move)?;
+    let x = lexer.read_number::<u8>().map_err(invalid_move)?;
+    lexer.expect(" ").map_err(invalid_move)?;
+    let y = lexer.read_number::<u8>().map_err(invalid_move)?;
+
+    // Clock fields are only used when numeric, any other trailing tokens are ignored
+    let mut fields = lexer.read_while(|_| true).split_whitespace().map(|s| s.parse::<u32>().ok());
+    let time_left = match (fields.next().flatten(), fields.next().flatten()) {
+        (Some(seconds), periods) => Some(TimeLeft::new(seconds, periods)),
+        (None, _) => None,
+    };
+
+    Ok(GibRecord::Stone { move_number, player, coordinate: BoardCoordinate::new(x, y), time_left })
+}
+
+/// Extract handicap from line of form `INI <num> <num> <handicap>`
+fn parse_handicap_line(str: &str) -> LineResult<GibRecord> {
+    let invalid_handicap = LineError::from_lexer(GibParseErrorKind::InvalidHandicap);
+    let mut lexer = Lexer::new(str);
+    lexer.expect("INI ").map_err(invalid_handicap)?;
+
+    lexer.read_number::<i16>().map_err(invalid_handicap)?;
+    lexer.expect(" ").map_err(invalid_handicap)?;
+
+    lexer.read_number::<i16>().map_err(invalid_handicap)?;
+    lexer.expect(" ").map_err(invalid_handicap)?;
+
+    let handicap = lexer.read_number::<u8>().map_err(invalid_handicap)?;
+
+    Ok(GibRecord::Init { handicap })
+}
+
+impl LineError {
+    /// Returns a function that converts lexer errors into line errors of given kind
+    pub fn from_lexer(kind: fn(String) -> GibParseErrorKind) -> impl Fn(LexerError) -> LineError + Copy {
+        move |e| LineError { pos: e.pos, kind: kind(e.message) }
+    }
+
+    pub fn at(self, line: usize, line_text: &str) -> GibParseError {
+        let column = line_text.get(..self.pos).map(|s| s.chars().count()).unwrap_or(0) + 1;
+        GibParseError { kind: self.kind, line, column, line_text: line_text.to_string() }
+    }
+}
+
+impl Display for GibParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GibParseErrorKind::InvalidMetadataLine => write!(f, "invalid metadata line"),
+            GibParseErrorKind::InvalidMove(e) => write!(f, "invalid move: {}", e),
+            GibParseErrorKind::InvalidHandicap(e) => write!(f, "invalid handicap: {}", e),
+            GibParseErrorKind::InvalidPlayer(player) => write!(f, "invalid player '{}'", player),
+            GibParseErrorKind::InvalidDate(e) => write!(f, "invalid date: {}", e),
+            GibParseErrorKind::UnknownSection(name) => write!(f, "unknown section '{}'", name),
+        }
+    }
+}
+
+impl Display for GibParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {} in '{}'", self.line, self.column, self.kind, self.line_text)
+    }
+}
+
+impl Error for GibParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: &str = "\\HS\r\n\\[GAMEWHITENAME=TheWhite (2D)\\]\r\n\\[GAMEFOO=bar\\]\r\n\\HE\r\n\\GS\r\n2 1 0\r\n186 0 &4\r\n\
+                       INI 0 1 0 &4\r\nSTO 0 2 1 16 3\r\nSKI 0 3\r\n\\GE\r\n";
+
+    fn records(section: &GibSection) -> Vec<&GibRecord> {
+        section.lines.iter().map(|l| &l.record).collect()
+    }
+
+    #[test]
+    fn test_sections() {
+        let document = GibDocument::parse(GIB);
+
+        let header = document.section(GibSectionKind::Header).unwrap();
+        assert!(header.closed);
+        assert_eq!(records(header), vec![
+            &GibRecord::Attribute { key: "GAMEWHITENAME".to_string(), value: "TheWhite (2D)".to_string() },
+            &GibRecord::Attribute { key: "GAMEFOO".to_string(), value: "bar".to_string() },
+        ]);
+
+        let game = document.section(GibSectionKind::Game).unwrap();
+        assert_eq!(records(game), vec![
+            &GibRecord::Unknown,
+            &GibRecord::Unknown,
+            &GibRecord::Init { handicap: 0 },
+            &GibRecord::Stone { move_number: 2, player: PlayerColor::Black, coordinate: BoardCoordinate::new(16, 3), time_left: None },
+            &GibRecord::Skip,
+        ]);
+        assert_eq!(game.lines[1].text, "186 0 &4");
+        assert_eq!(game.lines[1].number, 7);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(GibDocument::parse(GIB).to_gib(), GIB);
+
+        let unix = GIB.replace("\r\n", "\n");
+        assert_eq!(GibDocument::parse(&unix).to_gib(), unix);
+
+        let truncated = "foo\n\\HS\n\\[GAMEFOO=bar\\]\n\\GS\nSTO 0 2 1 16 3";
+        assert_eq!(GibDocument::parse(truncated).to_gib(), truncated);
+
+        let mixed = "\\HS\r\n\\[GAMEFOO=bar\\]\n\\HE\r\n\r\n\\GS\nSTO 0 2 1 16 3\r\n";
+        assert_eq!(GibDocument::parse(mixed).to_gib(), mixed);
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let gib = "\\HS\r\n\\[GAMEFOO\\]\r\n\\HE\r\n\\GS\r\nINI 0 1 x &4\r\nSTO 0 2 3 16 3\r\n\\GE\r\n";
+        let document = GibDocument::parse(gib);
+        assert_eq!(document.to_gib(), gib);
+
+        let errors: Vec<(usize, &GibParseErrorKind)> = document.errors().iter().map(|e| (e.line, &e.kind)).collect();
+        assert_eq!(errors, vec![
+            (2, &GibParseErrorKind::InvalidMetadataLine),
+            (5, &GibParseErrorKind::InvalidHandicap("expected number".to_string())),
+            (6, &GibParseErrorKind::InvalidPlayer("3".to_string())),
+        ]);
+        assert!(document.lines().filter(|l| l.text != "\\HS" && l.text != "\\HE" && l.text != "\\GS" && l.text != "\\GE")
+            .all(|l| l.record == GibRecord::Unknown));
+    }
+
+    #[test]
+    fn test_unclosed_sections() {
+        let document = GibDocument::parse("foo\n\\HS\n\\[GAMEFOO=bar\\]\n\\GS\nSTO 0 2 1 16 3\n\\HE");
+
+        assert_eq!(document.entries().len(), 3);
+        assert_eq!(document.entries()[0], GibEntry::Line(GibLine { number: 1, text: "foo".to_string(), ending: "\n", record: GibRecord::Unknown }));
+        assert!(!document.section(GibSectionKind::Header).unwrap().closed);
+
+        // End marker of another section is just a line in the current one
+        let game = document.section(GibSectionKind::Game).unwrap();
+        assert!(!game.closed);
+        assert_eq!(game.lines.len(), 2);
+        assert_eq!(game.lines[1].record, GibRecord::SectionMarker);
+    }
+}
@@ -1,21 +1,30 @@
 //! Common Go specific definitions without dependencies to any file format
+use std::error::Error;
 use std::fmt::{Debug, Formatter, Display};
 use std::fmt;
-use crate::go::GoMove::PlaceStone;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+use crate::go::GoMove::{PlaceStone, Pass};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PlayerColor {
     Black,
     White,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BoardCoordinate {
     pub x: u8,
     pub y: u8,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
 pub enum GoMove {
     PlaceStone { player: PlayerColor, coordinate: BoardCoordinate },
     Pass { player: PlayerColor },
@@ -25,21 +34,71 @@ impl GoMove {
     pub fn place_stone(player: PlayerColor, (x, y): (u8, u8)) -> GoMove {
         PlaceStone { player, coordinate: BoardCoordinate::new(x, y) }
     }
+
+    pub fn player(&self) -> PlayerColor {
+        match *self {
+            PlaceStone { player, .. } => player,
+            Pass { player } => player,
+        }
+    }
+
+    /// Returns the point where the stone was placed, or None for passes
+    pub fn coordinate(&self) -> Option<BoardCoordinate> {
+        match *self {
+            PlaceStone { coordinate, .. } => Some(coordinate),
+            Pass { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
 pub struct Handicap {
     stones: u8
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "f32", into = "f32"))]
 pub struct Score {
     /// Value of score in fixed point, multiplied by 10. Eg. 6.5 is represented as 65
     value: i16
 }
 
+/// Time settings of a game: main time followed by optional byo-yomi periods or
+/// a Fischer increment.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeControl {
+    /// Main time in seconds
+    main_time: u32,
+    byo_yomi: Option<ByoYomi>,
+    /// Seconds added to the clock after each move
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    increment: Option<u32>,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-#[allow(unused)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ByoYomi {
+    periods: u32,
+    /// Length of a single period in seconds
+    period_time: u32,
+}
+
+/// State of player's clock after a move
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeLeft {
+    /// Seconds left in main time, or in the current byo-yomi period
+    seconds: u32,
+    /// Byo-yomi periods left, if known
+    periods: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GameResult {
     Jigo,
     Count(PlayerColor, Option<Score>),
@@ -48,6 +107,38 @@ pub enum GameResult {
     Forfeit(PlayerColor),
 }
 
+/// Board sizes that can be written as SGF, which uses a single letter for each coordinate
+pub(crate) const BOARD_SIZES: RangeInclusive<u8> = 2..=25;
+
+/// Board position that is updated by playing moves on it
+#[derive(Debug, Clone)]
+pub struct Board {
+    size: u8,
+    /// Points of the board in row-major order
+    points: Vec<Option<PlayerColor>>,
+    /// Stones captured by black and white, respectively
+    prisoners: (u32, u32),
+    /// Point where the next player may not play because it would retake a ko
+    ko: Option<BoardCoordinate>,
+    moves_played: usize,
+}
+
+/// Error returned when trying to play an illegal move on a `Board`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IllegalMove {
+    /// 1-based number of the offending move
+    pub move_number: usize,
+    pub reason: IllegalMoveReason,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IllegalMoveReason {
+    OffBoard(BoardCoordinate),
+    Occupied(BoardCoordinate),
+    Suicide(BoardCoordinate),
+    Ko(BoardCoordinate),
+}
+
 impl PlayerColor {
     pub fn pick<T>(&self, black_option: T, white_option: T) -> T {
         match self {
@@ -55,6 +146,10 @@ impl PlayerColor {
             PlayerColor::White => white_option
         }
     }
+
+    pub fn opponent(&self) -> PlayerColor {
+        self.pick(PlayerColor::White, PlayerColor::Black)
+    }
 }
 
 impl Handicap {
@@ -66,13 +161,26 @@ impl Handicap {
         }
     }
 
-    // TODO: support various board sizes and handicaps greater than 9
-    pub fn handicap_points(&self) -> Vec<BoardCoordinate> {
+    pub fn stones(&self) -> u8 {
+        self.stones
+    }
+
+    /// Returns the fixed handicap placement for given board size. Up to nine stones are placed
+    /// on the star points. Larger handicaps continue on the fourth line halfway between the star
+    /// points and then on the points diagonally between the corner star points and the center.
+    /// Returns an error if the stones don't fit on the board, eg. more than 21 stones or boards
+    /// too small to have separate star points.
+    pub fn handicap_points(&self, board_size: u8) -> Result<Vec<BoardCoordinate>, String> {
+        let error = || format!("can't place {} handicap stones on {}x{} board", self.stones, board_size, board_size);
         let mut stones = self.stones;
 
         let mut result = vec![];
 
-        let board_size = 19;
+        // Star points are on the fourth line, or on the third line on small boards
+        let edge = if board_size >= 13 { 3 } else { 2 };
+        if board_size <= 2 * edge {
+            return Err(error());
+        }
         let max = board_size - 1;
         let mid = max / 2;
 
@@ -81,15 +189,105 @@ impl Handicap {
             stones -= 1;
         }
 
-        let candidates: Vec<(u8, u8)> = vec![
-            (3, max - 3), (max - 3, 3), (max - 3, max - 3), (3, 3), (3, mid), (max - 3, mid), (mid, 3), (mid, max - 3), (mid, mid)
-        ];
+        let candidates: Vec<(u8, u8)> = if stones > 9 {
+            result.extend(Handicap::from(9).unwrap().handicap_points(board_size).map_err(|_| error())?);
+            stones -= 9;
+
+            let near = (edge + mid) / 2;
+            let far = max - near;
+            vec![
+                (near, edge), (far, max - edge), (edge, far), (max - edge, near),
+                (far, edge), (near, max - edge), (edge, near), (max - edge, far),
+                (near, near), (far, far), (near, far), (far, near)
+            ]
+        } else {
+            vec![
+                (edge, max - edge), (max - edge, edge), (max - edge, max - edge), (edge, edge),
+                (edge, mid), (max - edge, mid), (mid, edge), (mid, max - edge), (mid, mid)
+            ]
+        };
 
+        if candidates.len() < stones as usize {
+            return Err(error());
+        }
         for &(x, y) in candidates.iter().take(stones as usize) {
             result.push(BoardCoordinate::new(x, y));
         }
 
-        result
+        // On small boards some of the points are the same
+        let mut unique = result.clone();
+        unique.sort_by_key(|p| (p.x, p.y));
+        unique.dedup();
+        if unique.len() != result.len() {
+            return Err(error());
+        }
+
+        Ok(result)
+    }
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<PlayerColor> {
+        match *self {
+            GameResult::Jigo => None,
+            GameResult::Count(winner, _) | GameResult::Resign(winner) | GameResult::Time(winner) | GameResult::Forfeit(winner) => Some(winner),
+        }
+    }
+}
+
+impl TimeControl {
+    pub fn new(main_time: u32, byo_yomi: Option<ByoYomi>) -> TimeControl {
+        TimeControl { main_time, byo_yomi, increment: None }
+    }
+
+    /// Returns Fischer time settings, or plain main time if the increment is zero
+    pub fn with_increment(main_time: u32, increment: u32) -> TimeControl {
+        TimeControl { main_time, byo_yomi: None, increment: Some(increment).filter(|&i| i > 0) }
+    }
+
+    pub fn main_time(&self) -> u32 {
+        self.main_time
+    }
+
+    pub fn byo_yomi(&self) -> Option<ByoYomi> {
+        self.byo_yomi
+    }
+
+    pub fn increment(&self) -> Option<u32> {
+        self.increment
+    }
+}
+
+impl ByoYomi {
+    /// Returns byo-yomi settings, or None if there is no byo-yomi at all
+    pub fn from(periods: u32, period_time: u32) -> Option<ByoYomi> {
+        if periods > 0 && period_time > 0 {
+            Some(ByoYomi { periods, period_time })
+        } else {
+            None
+        }
+    }
+
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    pub fn period_time(&self) -> u32 {
+        self.period_time
+    }
+}
+
+impl TimeLeft {
+    pub fn new(seconds: u32, periods: Option<u32>) -> TimeLeft {
+        TimeLeft { seconds, periods }
+    }
+
+    pub fn seconds(&self) -> u32 {
+        self.seconds
+    }
+
+    pub fn periods(&self) -> Option<u32> {
+        self.periods
     }
 }
 
@@ -100,10 +298,192 @@ impl BoardCoordinate {
     }
 }
 
+impl Board {
+    pub fn new(size: u8) -> Board {
+        Board {
+            size,
+            points: vec![None; size as usize * size as usize],
+            prisoners: (0, 0),
+            ko: None,
+            moves_played: 0,
+        }
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Returns the color of the stone at given point, or None if the point is empty or off the board
+    pub fn get(&self, coordinate: BoardCoordinate) -> Option<PlayerColor> {
+        self.index(coordinate).and_then(|i| self.points[i])
+    }
+
+    /// Returns the number of stones captured by given player
+    pub fn prisoners(&self, color: PlayerColor) -> u32 {
+        color.pick(self.prisoners.0, self.prisoners.1)
+    }
+
+    /// Places a setup stone, like a handicap stone, without checking captures. Errors
+    /// are reported with the number of the previous move, ie. 0 before the first move.
+    pub fn add_stone(&mut self, color: PlayerColor, coordinate: BoardCoordinate) -> Result<(), IllegalMove> {
+        let move_number = self.moves_played;
+        let index = self.index(coordinate)
+            .ok_or(IllegalMove { move_number, reason: IllegalMoveReason::OffBoard(coordinate) })?;
+        if self.points[index].is_some() {
+            return Err(IllegalMove { move_number, reason: IllegalMoveReason::Occupied(coordinate) });
+        }
+
+        self.points[index] = Some(color);
+        Ok(())
+    }
+
+    /// Plays a move, removing any stones it captures. Moves are not required to alternate
+    /// between the players, since that would rule out handicap placements and such.
+    pub fn play(&mut self, mv: &GoMove) -> Result<(), IllegalMove> {
+        let move_number = self.moves_played + 1;
+        let error = |reason| IllegalMove { move_number, reason };
+
+        if let PlaceStone { player, coordinate } = *mv {
+            let index = self.index(coordinate).ok_or_else(|| error(IllegalMoveReason::OffBoard(coordinate)))?;
+            if self.points[index].is_some() {
+                return Err(error(IllegalMoveReason::Occupied(coordinate)));
+            }
+            if self.ko == Some(coordinate) {
+                return Err(error(IllegalMoveReason::Ko(coordinate)));
+            }
+
+            self.points[index] = Some(player);
+
+            let mut captured = Vec::new();
+            for neighbor in self.neighbors(coordinate) {
+                if self.get(neighbor) == Some(player.opponent()) {
+                    let (group, liberties) = self.group(neighbor);
+                    if liberties == 0 {
+                        captured.extend(group);
+                    }
+                }
+            }
+
+            for &point in &captured {
+                if let Some(i) = self.index(point) {
+                    self.points[i] = None;
+                }
+            }
+
+            let (own_group, own_liberties) = self.group(coordinate);
+            if own_liberties == 0 {
+                self.points[index] = None;
+                return Err(error(IllegalMoveReason::Suicide(coordinate)));
+            }
+
+            match player {
+                PlayerColor::Black => self.prisoners.0 += captured.len() as u32,
+                PlayerColor::White => self.prisoners.1 += captured.len() as u32,
+            }
+
+            // Capturing a single stone with a single stone that is left in atari creates a ko
+            self.ko = if captured.len() == 1 && own_group.len() == 1 && own_liberties == 1 {
+                Some(captured[0])
+            } else {
+                None
+            };
+        } else {
+            self.ko = None;
+        }
+
+        self.moves_played += 1;
+        Ok(())
+    }
+
+    fn index(&self, coordinate: BoardCoordinate) -> Option<usize> {
+        if coordinate.x < self.size && coordinate.y < self.size {
+            Some(coordinate.y as usize * self.size as usize + coordinate.x as usize)
+        } else {
+            None
+        }
+    }
+
+    fn neighbors(&self, coordinate: BoardCoordinate) -> Vec<BoardCoordinate> {
+        let BoardCoordinate { x, y } = coordinate;
+        let mut result = Vec::with_capacity(4);
+
+        if x > 0 { result.push(BoardCoordinate::new(x - 1, y)) }
+        if y > 0 { result.push(BoardCoordinate::new(x, y - 1)) }
+        if x + 1 < self.size { result.push(BoardCoordinate::new(x + 1, y)) }
+        if y + 1 < self.size { result.push(BoardCoordinate::new(x, y + 1)) }
+
+        result
+    }
+
+    /// Returns the stones of the group at given point and the number of its liberties
+    fn group(&self, coordinate: BoardCoordinate) -> (Vec<BoardCoordinate>, usize) {
+        let color = self.get(coordinate);
+        let mut stones = vec![coordinate];
+        let mut liberties = Vec::new();
+        let mut visited = vec![false; self.points.len()];
+        visited[self.index(coordinate).unwrap()] = true;
+
+        let mut i = 0;
+        while i < stones.len() {
+            for neighbor in self.neighbors(stones[i]) {
+                let index = self.index(neighbor).unwrap();
+                if visited[index] {
+                    continue;
+                }
+                visited[index] = true;
+
+                match self.points[index] {
+                    None => liberties.push(neighbor),
+                    c if c == color => stones.push(neighbor),
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+
+        (stones, liberties.len())
+    }
+}
+
 impl Score {
     pub fn new(value: f32) -> Score {
         Score { value: (value * 10.0) as i16 }
     }
+
+    /// Returns the score multiplied by 10, which is how GIB files represent scores
+    pub fn tenths(&self) -> i16 {
+        self.value
+    }
+
+    pub fn value(&self) -> f32 {
+        f32::from(self.value) / 10.0
+    }
+}
+
+impl From<f32> for Score {
+    fn from(value: f32) -> Score {
+        Score::new(value)
+    }
+}
+
+impl From<Score> for f32 {
+    fn from(score: Score) -> f32 {
+        score.value()
+    }
+}
+
+impl TryFrom<u8> for Handicap {
+    type Error = String;
+
+    fn try_from(stones: u8) -> Result<Handicap, String> {
+        Handicap::from(stones).ok_or_else(|| format!("invalid handicap {}", stones))
+    }
+}
+
+impl From<Handicap> for u8 {
+    fn from(handicap: Handicap) -> u8 {
+        handicap.stones
+    }
 }
 
 impl Display for Score {
@@ -126,6 +506,20 @@ impl Debug for BoardCoordinate {
     }
 }
 
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (description, coordinate) = match self.reason {
+            IllegalMoveReason::OffBoard(c) => ("is off the board", c),
+            IllegalMoveReason::Occupied(c) => ("is on an occupied point", c),
+            IllegalMoveReason::Suicide(c) => ("is suicide", c),
+            IllegalMoveReason::Ko(c) => ("retakes a ko", c),
+        };
+        write!(f, "move {} at ({}, {}) {}", self.move_number, coordinate.x, coordinate.y, description)
+    }
+}
+
+impl Error for IllegalMove {}
+
 impl Debug for Score {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(&self.to_string())
@@ -146,10 +540,141 @@ mod tests {
         assert_eq!(Score::new(6.0).to_string(), "6");
     }
 
+    #[test]
+    fn test_accessors() {
+        assert_eq!(Score::new(-6.5).value(), -6.5);
+        assert_eq!(GoMove::place_stone(PlayerColor::White, (3, 4)).coordinate(), Some(BoardCoordinate::new(3, 4)));
+        assert_eq!(GoMove::Pass { player: PlayerColor::Black }.coordinate(), None);
+        assert_eq!(GameResult::Resign(PlayerColor::White).winner(), Some(PlayerColor::White));
+        assert_eq!(GameResult::Jigo.winner(), None);
+    }
+
+    fn play_all(board: &mut Board, moves: &[(PlayerColor, u8, u8)]) {
+        for &(player, x, y) in moves {
+            board.play(&GoMove::place_stone(player, (x, y))).unwrap();
+        }
+    }
+
+    #[test]
+    fn board_captures() {
+        use PlayerColor::{Black, White};
+        let mut board = Board::new(9);
+
+        // Capture a single stone in the corner and then a group of two on the edge
+        play_all(&mut board, &[(White, 0, 0), (Black, 1, 0), (White, 4, 0), (Black, 0, 1)]);
+        assert_eq!(board.get(BoardCoordinate::new(0, 0)), None);
+        assert_eq!(board.prisoners(Black), 1);
+
+        play_all(&mut board, &[(White, 5, 0), (Black, 3, 0), (Black, 4, 1), (Black, 5, 1)]);
+        assert_eq!(board.get(BoardCoordinate::new(4, 0)), Some(White));
+        board.play(&GoMove::place_stone(Black, (6, 0))).unwrap();
+        assert_eq!(board.get(BoardCoordinate::new(4, 0)), None);
+        assert_eq!(board.get(BoardCoordinate::new(5, 0)), None);
+        assert_eq!(board.prisoners(Black), 3);
+        assert_eq!(board.prisoners(White), 0);
+    }
+
+    #[test]
+    fn board_rejects_illegal_moves() {
+        use PlayerColor::{Black, White};
+        let mut board = Board::new(9);
+
+        play_all(&mut board, &[(Black, 1, 0), (White, 5, 5), (Black, 0, 1)]);
+
+        assert_eq!(board.play(&GoMove::place_stone(White, (1, 0))),
+                   Err(IllegalMove { move_number: 4, reason: IllegalMoveReason::Occupied(BoardCoordinate::new(1, 0)) }));
+        assert_eq!(board.play(&GoMove::place_stone(White, (9, 0))),
+                   Err(IllegalMove { move_number: 4, reason: IllegalMoveReason::OffBoard(BoardCoordinate::new(9, 0)) }));
+        assert_eq!(board.play(&GoMove::place_stone(White, (0, 0))),
+                   Err(IllegalMove { move_number: 4, reason: IllegalMoveReason::Suicide(BoardCoordinate::new(0, 0)) }));
+        assert_eq!(board.get(BoardCoordinate::new(0, 0)), None);
+
+        // Filling own last liberty is fine if it captures something
+        play_all(&mut board, &[(White, 2, 0), (White, 1, 1), (White, 0, 2)]);
+        board.play(&GoMove::place_stone(White, (0, 0))).unwrap();
+        assert_eq!(board.prisoners(White), 2);
+    }
+
+    #[test]
+    fn board_setup_stones() {
+        use PlayerColor::{Black, White};
+        let mut board = Board::new(9);
+
+        board.add_stone(Black, BoardCoordinate::new(2, 2)).unwrap();
+        assert_eq!(board.get(BoardCoordinate::new(2, 2)), Some(Black));
+        assert_eq!(board.add_stone(Black, BoardCoordinate::new(2, 2)),
+                   Err(IllegalMove { move_number: 0, reason: IllegalMoveReason::Occupied(BoardCoordinate::new(2, 2)) }));
+        assert_eq!(board.add_stone(Black, BoardCoordinate::new(2, 9)),
+                   Err(IllegalMove { move_number: 0, reason: IllegalMoveReason::OffBoard(BoardCoordinate::new(2, 9)) }));
+
+        board.play(&GoMove::place_stone(White, (6, 6))).unwrap();
+        assert_eq!(board.play(&GoMove::place_stone(White, (2, 2))).unwrap_err().move_number, 2);
+    }
+
+    #[test]
+    fn board_ko() {
+        use PlayerColor::{Black, White};
+        let mut board = Board::new(9);
+
+        play_all(&mut board, &[(Black, 3, 4), (White, 5, 3), (Black, 4, 3), (White, 5, 5), (Black, 4, 5), (White, 6, 4), (White, 4, 4)]);
+
+        // Black takes the ko and white may not retake it immediately
+        board.play(&GoMove::place_stone(Black, (5, 4))).unwrap();
+        assert_eq!(board.prisoners(Black), 1);
+        assert_eq!(board.play(&GoMove::place_stone(White, (4, 4))),
+                   Err(IllegalMove { move_number: 9, reason: IllegalMoveReason::Ko(BoardCoordinate::new(4, 4)) }));
+
+        // After ko threats white may retake
+        play_all(&mut board, &[(White, 8, 8), (Black, 8, 0), (White, 4, 4)]);
+        assert_eq!(board.prisoners(White), 1);
+        assert_eq!(board.play(&GoMove::place_stone(Black, (5, 4))).unwrap_err().reason, IllegalMoveReason::Ko(BoardCoordinate::new(5, 4)));
+
+        // Passing also resolves the ko
+        board.play(&GoMove::Pass { player: Black }).unwrap();
+        board.play(&GoMove::Pass { player: White }).unwrap();
+        board.play(&GoMove::place_stone(Black, (5, 4))).unwrap();
+    }
+
+    fn handicap_points_on(stones: u8, board_size: u8) -> Vec<(u8, u8)> {
+        Handicap::from(stones).unwrap().handicap_points(board_size).unwrap().iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn large_handicap_placement() {
+        let nine_stones = handicap_points_on(9, 19);
+
+        assert_eq!(handicap_points_on(10, 19), [nine_stones.clone(), vec![(6, 3)]].concat());
+        assert_eq!(handicap_points_on(13, 19), [nine_stones.clone(), vec![(6, 3), (12, 15), (3, 12), (15, 6)]].concat());
+        assert_eq!(handicap_points_on(17, 19), [nine_stones.clone(), vec![(6, 3), (12, 15), (3, 12), (15, 6), (12, 3), (6, 15), (3, 6), (15, 12)]].concat());
+        assert_eq!(handicap_points_on(21, 19)[17..], [(6, 6), (12, 12), (6, 12), (12, 6)]);
+        assert_eq!(Handicap::from(22).unwrap().handicap_points(19), Err("can't place 22 handicap stones on 19x19 board".to_string()));
+
+        assert_eq!(handicap_points_on(13, 9)[9..], [(3, 2), (5, 6), (2, 5), (6, 3)]);
+        assert_eq!(handicap_points_on(13, 13)[9..], [(4, 3), (8, 9), (3, 8), (9, 4)]);
+    }
+
+    #[test]
+    fn handicap_placement_on_small_boards() {
+        assert_eq!(handicap_points_on(2, 13), vec![(3, 9), (9, 3)]);
+        assert_eq!(handicap_points_on(5, 13), vec![(6, 6), (3, 9), (9, 3), (9, 9), (3, 3)]);
+        assert_eq!(handicap_points_on(9, 13), vec![(3, 9), (9, 3), (9, 9), (3, 3), (3, 6), (9, 6), (6, 3), (6, 9), (6, 6)]);
+
+        assert_eq!(handicap_points_on(2, 9), vec![(2, 6), (6, 2)]);
+        assert_eq!(handicap_points_on(4, 9), vec![(2, 6), (6, 2), (6, 6), (2, 2)]);
+        assert_eq!(handicap_points_on(7, 9), vec![(4, 4), (2, 6), (6, 2), (6, 6), (2, 2), (2, 4), (6, 4)]);
+        assert_eq!(handicap_points_on(9, 7).len(), 9);
+
+        for size in 2..=5 {
+            assert!(Handicap::from(2).unwrap().handicap_points(size).is_err());
+        }
+        assert!(Handicap::from(5).unwrap().handicap_points(6).is_err());
+        assert!(Handicap::from(10).unwrap().handicap_points(7).is_err());
+    }
+
     #[test]
     fn handicap_placement() {
         fn handicap_points(stones: u8) -> Vec<(u8, u8)> {
-            Handicap::from(stones).unwrap().handicap_points().iter().map(|p| (p.x, p.y)).collect()
+            handicap_points_on(stones, 19)
         }
 
         assert_eq!(handicap_points(2), vec![(3, 15), (15, 3)]);
@@ -0,0 +1,329 @@
+//! Index of the games in an archive, stored as CSV so it can also be opened in a spreadsheet
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use gib2sgf::{ByoYomi, GameResult, GibMetadata, LocalDate, PlayerColor, Score, TimeControl, ToSgf};
+use crate::info::format_player;
+
+/// Default file name of the index
+pub const DEFAULT_INDEX: &str = "gib2sgf-index.csv";
+
+const COLUMNS: &[&str] = &["path", "black", "black_rank", "white", "white_rank", "date", "result",
+    "komi", "handicap", "moves", "time_control"];
+
+/// Metadata of a single game in the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub black_nick: Option<String>,
+    pub black_rank: Option<String>,
+    pub white_nick: Option<String>,
+    pub white_rank: Option<String>,
+    pub date: Option<LocalDate>,
+    pub result: Option<GameResult>,
+    pub komi: Option<Score>,
+    pub handicap: Option<u8>,
+    pub moves: usize,
+    pub time_control: Option<TimeControl>,
+}
+
+impl IndexEntry {
+    pub fn new(path: PathBuf, metadata: GibMetadata, moves: usize) -> IndexEntry {
+        IndexEntry {
+            path,
+            black_nick: metadata.black_nick,
+            black_rank: metadata.black_rank,
+            white_nick: metadata.white_nick,
+            white_rank: metadata.white_rank,
+            date: metadata.date,
+            result: metadata.result,
+            komi: metadata.komi,
+            handicap: metadata.handicap.map(u8::from),
+            moves,
+            time_control: metadata.time_control,
+        }
+    }
+
+    pub fn nick(&self, color: PlayerColor) -> Option<&str> {
+        color.pick(&self.black_nick, &self.white_nick).as_deref()
+    }
+
+    pub fn rank(&self, color: PlayerColor) -> Option<&str> {
+        color.pick(&self.black_rank, &self.white_rank).as_deref()
+    }
+
+    /// Returns the colour played by given player, comparing nicks case-insensitively
+    pub fn color_of(&self, player: &str) -> Option<PlayerColor> {
+        [PlayerColor::Black, PlayerColor::White].iter().copied()
+            .find(|&color| self.nick(color).is_some_and(|nick| nick.eq_ignore_ascii_case(player)))
+    }
+
+    fn to_record(&self) -> Vec<String> {
+        let text = |s: &Option<String>| s.clone().unwrap_or_default();
+        vec![
+            self.path.display().to_string(),
+            text(&self.black_nick),
+            text(&self.black_rank),
+            text(&self.white_nick),
+            text(&self.white_rank),
+            self.date.map(|d| d.iso_string()).unwrap_or_default(),
+            self.result.map(|r| r.to_sgf()).unwrap_or_default(),
+            self.komi.map(|k| k.to_string()).unwrap_or_default(),
+            self.handicap.map(|h| h.to_string()).unwrap_or_default(),
+            self.moves.to_string(),
+            self.time_control.map(format_time_control).unwrap_or_default(),
+        ]
+    }
+
+    fn from_record(record: &[String]) -> Result<IndexEntry, String> {
+        if record.len() != COLUMNS.len() {
+            return Err(format!("expected {} columns, got {}", COLUMNS.len(), record.len()));
+        }
+        let value = |i: usize| Some(record[i].as_str()).filter(|s| !s.is_empty());
+        let text = |i: usize| value(i).map(|s| s.to_string());
+        let invalid = |i: usize| format!("invalid {} '{}'", COLUMNS[i], record[i]);
+
+        Ok(IndexEntry {
+            path: PathBuf::from(&record[0]),
+            black_nick: text(1),
+            black_rank: text(2),
+            white_nick: text(3),
+            white_rank: text(4),
+            date: value(5).map(|s| LocalDate::parse_iso(s).map_err(|_| invalid(5))).transpose()?,
+            result: value(6).map(|s| s.parse()).transpose()?,
+            komi: value(7).map(|s| s.parse::<f32>().map(Score::new).map_err(|_| invalid(7))).transpose()?,
+            handicap: value(8).map(|s| s.parse().map_err(|_| invalid(8))).transpose()?,
+            moves: record[9].parse().map_err(|_| invalid(9))?,
+            time_control: value(10).map(|s| parse_time_control(s).ok_or_else(|| invalid(10))).transpose()?,
+        })
+    }
+}
+
+/// Formats the entry as a line of search results
+impl Display for IndexEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let player = |color| format_player(self.nick(color), self.rank(color));
+        let date = self.date.map(|d| d.iso_string()).unwrap_or_default();
+        let result = self.result.map(|r| r.to_sgf()).unwrap_or_default();
+        write!(f, "{:10}  {} - {}  {}  {}", date, player(PlayerColor::Black), player(PlayerColor::White), result, self.path.display())
+    }
+}
+
+/// Formats time control as main time in seconds, followed by byo-yomi or increment,
+/// eg. `600 3x30` or `600 +10`
+fn format_time_control(time_control: TimeControl) -> String {
+    match (time_control.byo_yomi(), time_control.increment()) {
+        (Some(byo_yomi), _) => format!("{} {}x{}", time_control.main_time(), byo_yomi.periods(), byo_yomi.period_time()),
+        (None, Some(increment)) => format!("{} +{}", time_control.main_time(), increment),
+        (None, None) => time_control.main_time().to_string(),
+    }
+}
+
+fn parse_time_control(str: &str) -> Option<TimeControl> {
+    let mut parts = str.split_whitespace();
+    let main_time = parts.next()?.parse().ok()?;
+    match parts.next() {
+        Some(overtime) => match overtime.strip_prefix('+') {
+            Some(increment) => Some(TimeControl::with_increment(main_time, increment.parse().ok()?)),
+            None => {
+                let (periods, period_time) = overtime.split_once('x')?;
+                Some(TimeControl::new(main_time, Some(ByoYomi::from(periods.parse().ok()?, period_time.parse().ok()?)?)))
+            }
+        },
+        None => Some(TimeControl::new(main_time, None)),
+    }
+}
+
+/// Writes the entries as CSV, with a header line
+pub fn write_index(entries: &[IndexEntry]) -> String {
+    let mut result = write_record(COLUMNS.iter());
+    for entry in entries {
+        result.push_str(&write_record(entry.to_record().iter()));
+    }
+    result
+}
+
+/// Reads an index written by `write_index`
+pub fn read_index(csv: &str) -> Result<Vec<IndexEntry>, String> {
+    let mut records = parse_csv(csv)?.into_iter();
+    match records.next() {
+        Some(header) if header == COLUMNS => {}
+        _ => return Err("not a gib2sgf index".to_string()),
+    }
+
+    records.enumerate()
+        .map(|(i, record)| IndexEntry::from_record(&record).map_err(|e| format!("line {}: {}", i + 2, e)))
+        .collect()
+}
+
+fn write_record(fields: impl Iterator<Item=impl AsRef<str>>) -> String {
+    let fields: Vec<String> = fields.map(|field| {
+        let field = field.as_ref();
+        if field.contains(['"', ',', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// Parses CSV as written by `write_record`, allowing quoted fields with line breaks
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Which results the search matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultFilter {
+    /// Games won by the searched player
+    Win,
+    /// Games lost by the searched player
+    Loss,
+    /// Results starting with given text in SGF format, eg. `B+` or `W+R`
+    Prefix(String),
+}
+
+/// Conditions for searching the index, all of which must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilter {
+    pub player: Option<String>,
+    pub from: Option<LocalDate>,
+    pub to: Option<LocalDate>,
+    pub result: Option<ResultFilter>,
+    /// Rank of the searched player, or of either player if no player is given
+    pub rank: Option<String>,
+}
+
+impl SearchFilter {
+    pub fn matches(&self, entry: &IndexEntry) -> bool {
+        let color = match &self.player {
+            Some(player) => match entry.color_of(player) {
+                Some(color) => Some(color),
+                None => return false,
+            },
+            None => None,
+        };
+
+        if self.from.is_some() && (entry.date.is_none() || entry.date < self.from) {
+            return false;
+        }
+        if self.to.is_some() && (entry.date.is_none() || entry.date > self.to) {
+            return false;
+        }
+
+        if let Some(rank) = &self.rank {
+            let colors = color.map_or(vec![PlayerColor::Black, PlayerColor::White], |c| vec![c]);
+            if !colors.iter().any(|&c| entry.rank(c).is_some_and(|r| r.eq_ignore_ascii_case(rank))) {
+                return false;
+            }
+        }
+
+        match &self.result {
+            Some(ResultFilter::Win) => color.is_some() && entry.result.and_then(|r| r.winner()) == color,
+            Some(ResultFilter::Loss) => color.is_some() && entry.result.and_then(|r| r.winner()) == color.map(|c| c.opponent()),
+            Some(ResultFilter::Prefix(prefix)) => entry.result.is_some_and(|r| r.to_sgf().to_ascii_uppercase().starts_with(&prefix.to_ascii_uppercase())),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Game between players given as `(nick, rank)`, also used by the tests of other modules
+    pub(crate) fn entry(black: (&str, &str), white: (&str, &str), date: &str, result: &str, moves: usize) -> IndexEntry {
+        IndexEntry {
+            path: PathBuf::from(format!("{}_{}.gib", black.0, white.0)),
+            black_nick: Some(black.0.to_string()),
+            black_rank: Some(black.1.to_string()),
+            white_nick: Some(white.0.to_string()),
+            white_rank: Some(white.1.to_string()),
+            date: LocalDate::parse_iso(date).ok(),
+            result: result.parse().ok(),
+            komi: Some(Score::new(6.5)),
+            handicap: None,
+            moves,
+            time_control: Some(TimeControl::new(600, ByoYomi::from(3, 30))),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut quoted = entry(("komula", "4D"), ("zang,\"pung\"", "5D"), "2020-04-01", "B+R", 211);
+        quoted.time_control = Some(TimeControl::with_increment(300, 10));
+        let mut empty = entry(("a", "4D"), ("b", "5D"), "", "", 211);
+        empty.black_rank = None;
+        empty.komi = None;
+        empty.handicap = Some(2);
+        let entries = vec![entry(("komula", "4D"), ("zangpung", "5D"), "2020-04-01", "W+3.5", 211), quoted, empty];
+
+        let csv = write_index(&entries);
+        assert!(csv.starts_with("path,black,black_rank,white,white_rank,date,result,komi,handicap,moves,time_control\n\
+                                 komula_zangpung.gib,komula,4D,zangpung,5D,2020-04-01,W+3.5,6.5,,211,600 3x30\n"));
+        assert_eq!(read_index(&csv), Ok(entries.clone()));
+        assert_eq!(entries[0].to_string(), "2020-04-01  komula (4D) - zangpung (5D)  W+3.5  komula_zangpung.gib");
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert_eq!(read_index("a,b\n"), Err("not a gib2sgf index".to_string()));
+        let csv = format!("{}x.gib,,,,,,,,,many,\n", write_index(&[]));
+        assert_eq!(read_index(&csv), Err("line 2: invalid moves 'many'".to_string()));
+    }
+
+    #[test]
+    fn test_search() {
+        let game = entry(("komula", "4D"), ("zangpung", "5D"), "2020-04-01", "W+R", 211);
+        let search = |filter: SearchFilter| filter.matches(&game);
+
+        assert!(search(SearchFilter::default()));
+        assert!(search(SearchFilter { player: Some("KOMULA".to_string()), ..SearchFilter::default() }));
+        assert!(!search(SearchFilter { player: Some("someone".to_string()), ..SearchFilter::default() }));
+
+        let date = |s| LocalDate::parse_iso(s).ok();
+        assert!(search(SearchFilter { from: date("2020-04-01"), to: date("2020-04-30"), ..SearchFilter::default() }));
+        assert!(!search(SearchFilter { from: date("2020-04-02"), ..SearchFilter::default() }));
+
+        let komula = |result, rank: Option<&str>| SearchFilter {
+            player: Some("komula".to_string()), result, rank: rank.map(|r| r.to_string()), ..SearchFilter::default()
+        };
+        assert!(search(komula(Some(ResultFilter::Loss), None)));
+        assert!(!search(komula(Some(ResultFilter::Win), None)));
+        assert!(search(komula(Some(ResultFilter::Prefix("w+r".to_string())), Some("4d"))));
+        assert!(!search(komula(None, Some("5D"))));
+        assert!(search(SearchFilter { rank: Some("5D".to_string()), ..SearchFilter::default() }));
+    }
+}
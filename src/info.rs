@@ -0,0 +1,171 @@
+//! Game information printed by the info command
+use std::path::PathBuf;
+use gib2sgf::{Gib, ToSgf};
+
+/// Summary of a GIB file, with the raw header attributes sorted by name
+#[derive(Debug, PartialEq)]
+pub struct GameInfo {
+    pub path: PathBuf,
+    pub black_nick: Option<String>,
+    pub black_rank: Option<String>,
+    pub white_nick: Option<String>,
+    pub white_rank: Option<String>,
+    pub date: Option<String>,
+    pub komi: Option<f32>,
+    pub handicap: Option<u8>,
+    pub result: Option<String>,
+    pub board_size: u8,
+    pub moves: usize,
+    pub attributes: Vec<(String, String)>,
+}
+
+/// Columns of the TSV output, attributes are written last as `NAME=VALUE` pairs
+const TSV_COLUMNS: &[&str] = &["path", "black", "black_rank", "white", "white_rank", "date", "komi",
+    "handicap", "result", "board_size", "moves", "attributes"];
+
+impl GameInfo {
+    pub fn new(path: PathBuf, gib: &Gib) -> GameInfo {
+        let metadata = gib.get_metadata();
+        let mut attributes: Vec<(String, String)> = gib.get_attributes()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        attributes.sort();
+
+        GameInfo {
+            path,
+            black_nick: metadata.black_nick,
+            black_rank: metadata.black_rank,
+            white_nick: metadata.white_nick,
+            white_rank: metadata.white_rank,
+            date: metadata.date.map(|d| d.iso_string()),
+            komi: metadata.komi.map(|k| k.value()),
+            handicap: metadata.handicap.map(u8::from),
+            result: metadata.result.map(|r| r.to_sgf()),
+            board_size: metadata.board_size,
+            moves: gib.get_moves().len(),
+            attributes,
+        }
+    }
+
+    /// Formats the information as aligned `Name: value` lines, leaving out missing values
+    pub fn to_table(&self) -> String {
+        let player = |nick: &Option<String>, rank: &Option<String>| Some(format_player(nick.as_deref(), rank.as_deref()));
+        let rows: Vec<(&str, String)> = vec![
+            ("Black", player(&self.black_nick, &self.black_rank)),
+            ("White", player(&self.white_nick, &self.white_rank)),
+            ("Date", self.date.clone()),
+            ("Komi", self.komi.map(|k| k.to_string())),
+            ("Handicap", self.handicap.map(|h| h.to_string())),
+            ("Result", self.result.clone()),
+            ("Board size", Some(self.board_size.to_string())),
+            ("Moves", Some(self.moves.to_string())),
+        ].into_iter().filter_map(|(name, value)| Some((name, value?))).collect();
+
+        let mut result = format!("{}\n", self.path.display());
+        // Attributes are indented two more columns, but their values are aligned with the rest
+        let width = rows.iter().map(|(name, _)| name.len() + 1)
+            .chain(self.attributes.iter().map(|(name, _)| name.len() + 3))
+            .max().unwrap_or(0);
+        for (name, value) in rows {
+            result.push_str(&format!("  {:width$}  {}\n", format!("{}:", name), value, width = width));
+        }
+        if !self.attributes.is_empty() {
+            result.push_str("  Attributes:\n");
+            for (name, value) in &self.attributes {
+                result.push_str(&format!("    {:width$}  {}\n", format!("{}:", name), value, width = width - 2));
+            }
+        }
+        result
+    }
+
+    pub fn tsv_header() -> String {
+        TSV_COLUMNS.join("\t")
+    }
+
+    pub fn to_tsv(&self) -> String {
+        let attributes: Vec<String> = self.attributes.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        let values = [
+            self.path.display().to_string(),
+            or_empty(&self.black_nick),
+            or_empty(&self.black_rank),
+            or_empty(&self.white_nick),
+            or_empty(&self.white_rank),
+            or_empty(&self.date),
+            or_empty(&self.komi),
+            or_empty(&self.handicap),
+            or_empty(&self.result),
+            self.board_size.to_string(),
+            self.moves.to_string(),
+            attributes.join(";"),
+        ];
+        values.iter().map(|v| tsv_escape(v)).collect::<Vec<_>>().join("\t")
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let attributes: serde_json::Map<String, serde_json::Value> = self.attributes.iter()
+            .map(|(name, value)| (name.clone(), serde_json::Value::from(value.as_str())))
+            .collect();
+        serde_json::json!({
+            "path": self.path,
+            "black": { "nick": self.black_nick, "rank": self.black_rank },
+            "white": { "nick": self.white_nick, "rank": self.white_rank },
+            "date": self.date,
+            "komi": self.komi,
+            "handicap": self.handicap,
+            "result": self.result,
+            "board_size": self.board_size,
+            "moves": self.moves,
+            "attributes": attributes,
+        })
+    }
+}
+
+/// Formats a player as `nick (rank)`, using `?` for a missing nick
+pub fn format_player(nick: Option<&str>, rank: Option<&str>) -> String {
+    match rank {
+        Some(rank) => format!("{} ({})", nick.unwrap_or("?"), rank),
+        None => nick.unwrap_or("?").to_string(),
+    }
+}
+
+fn or_empty(value: &Option<impl ToString>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Tabs and line breaks would break the columns, so replace them with spaces
+fn tsv_escape(value: &str) -> String {
+    value.replace(['\t', '\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_info() -> GameInfo {
+        let gib = Gib::parse("\\HS\r\n\\[GAMEBLACKNAME=komula (4D)\\]\r\n\\[GAMEWHITENAME=zangpung (4D)\\]\r\n\
+            \\[GAMEGONGJE=65\\]\r\n\\[GAMEPLACE=Tygem\tBaduk\\]\r\n\\HE\r\n\\GS\r\n\
+            INI 0 1 0 &4\r\n\
+            STO 0 2 1 15 3\r\n\
+            \\GE\r\n").unwrap();
+        GameInfo::new(PathBuf::from("game.gib"), &gib)
+    }
+
+    #[test]
+    fn test_table() {
+        let table = game_info().to_table();
+        assert!(table.starts_with("game.gib\n  Black:            komula (4D)\n  White:            zangpung (4D)\n  Komi:             6.5\n"));
+        assert!(table.contains("  Moves:            1\n  Attributes:\n"));
+        assert!(table.contains("    GAMEBLACKNAME:  komula (4D)\n    GAMEGONGJE:     65\n"));
+    }
+
+    #[test]
+    fn test_tsv() {
+        let info = game_info();
+        let header = GameInfo::tsv_header();
+        let row = info.to_tsv();
+        assert_eq!(header.split('\t').count(), row.split('\t').count());
+        assert!(row.starts_with("game.gib\tkomula\t4D\tzangpung\t4D\t\t6.5\t\t\t19\t1\tGAMEBLACKNAME=komula (4D);"));
+        assert!(row.ends_with("GAMEPLACE=Tygem Baduk;GAMEWHITENAME=zangpung (4D)"));
+    }
+}
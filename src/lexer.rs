@@ -1,6 +1,13 @@
 use std::str::FromStr;
 
-type LexerResult<T> = Result<T, String>;
+type LexerResult<T> = Result<T, LexerError>;
+
+/// Error with the byte offset where lexing failed
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LexerError {
+    pub pos: usize,
+    pub message: String,
+}
 
 pub struct Lexer<'a> {
     str: &'a str,
@@ -8,20 +15,28 @@ pub struct Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(str: &str) -> Lexer {
+    pub fn new(str: &str) -> Lexer<'_> {
         Lexer { str, pos: 0 }
     }
 
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
     pub fn read_number<T: FromStr>(&mut self) -> LexerResult<T> {
+        let start = self.pos;
         let str = self.read_while(|c| c.is_ascii_digit());
-        str.parse::<T>().map_err(|_| format!("not a number '{}'", str))
+        str.parse::<T>().map_err(|_| {
+            let message = if str.is_empty() { String::from("expected number") } else { format!("not a number '{}'", str) };
+            LexerError { pos: start, message }
+        })
     }
 
     pub fn expect(&mut self, str: &str) -> LexerResult<()> {
         if self.optional(str) {
             Ok(())
         } else {
-            Err(format!("Expected '{}', but did not see", str))
+            Err(LexerError { pos: self.pos, message: format!("expected '{}'", str) })
         }
     }
 
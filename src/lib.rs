@@ -1,12 +1,38 @@
+//! Converts GIB files used by Tygem to SGF, and back.
+//!
+//! Besides the conversion functions, the parsed game record is available as [`Gib`]
+//! and SGF trees can be built, parsed and written with [`SgfCollection`].
+//!
+//! ```
+//! use gib2sgf::{Gib, PlayerColor};
+//!
+//! let gib = Gib::parse("\\HS\n\\[GAMEBLACKNAME=komu (4D)\\]\n\\HE\n\\GS\nINI 0 1 0 &4\nSTO 0 2 1 3 3\n\\GE\n").unwrap();
+//! assert_eq!(gib.get_nick(PlayerColor::Black), Some("komu"));
+//! assert_eq!(gib.get_moves().len(), 1);
+//! ```
 mod time;
 mod gib;
+mod gib_document;
 mod go;
 mod sgf;
+mod sgf_parser;
 mod gib2sgf;
+mod sgf2gib;
 mod lexer;
+mod encoding;
 use wasm_bindgen::prelude::*;
 
-pub use crate::gib2sgf::gib_to_sgf;
+pub use crate::gib2sgf::{gib_to_sgf, gib_to_sgf_bytes, gib_to_sgf_validated, gib_to_sgf_with_options};
+pub use crate::gib2sgf::{ConversionOptions, Rules, DateFormat, Validation, Conversion, ConversionError};
+pub use crate::gib::{Gib, GibMetadata};
+pub use crate::go::{PlayerColor, BoardCoordinate, GoMove, Handicap, Score, TimeControl, ByoYomi, TimeLeft, GameResult};
+pub use crate::go::{Board, IllegalMove, IllegalMoveReason};
+pub use crate::time::{LocalDate, LocalDateTime};
+pub use crate::gib_document::{GibDocument, GibEntry, GibSection, GibSectionKind, GibLine, GibRecord, GibParseError, GibParseErrorKind};
+pub use crate::encoding::decode_gib;
+pub use crate::sgf::{SgfCollection, SgfTree, SgfNode, ToSgf};
+pub use crate::sgf_parser::SgfParseError;
+pub use crate::sgf2gib::{sgf_to_gib, sgf_to_gib_with_warnings, collection_to_gib, collection_to_gib_with_warnings, GibConversion, SgfToGibError};
 
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
@@ -15,3 +41,58 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 pub fn convert_gib_to_sgf(str: &str) -> Option<String> {
     gib_to_sgf(str).ok()
 }
+
+#[wasm_bindgen]
+pub fn convert_gib_bytes_to_sgf(bytes: &[u8]) -> Option<String> {
+    gib_to_sgf_bytes(bytes).ok()
+}
+
+/// Conversion options for JavaScript, which can't construct Rust structs directly
+#[wasm_bindgen(js_name = ConversionOptions)]
+#[derive(Default)]
+pub struct JsConversionOptions {
+    options: ConversionOptions,
+}
+
+#[wasm_bindgen(js_class = ConversionOptions)]
+impl JsConversionOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsConversionOptions {
+        JsConversionOptions::default()
+    }
+
+    /// Sets rules to "japanese", "korean" or "chinese"
+    pub fn set_rules(&mut self, rules: &str) -> Result<(), JsValue> {
+        self.options.rules = rules.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
+    /// Sets the board size, between 2 and 25
+    pub fn set_board_size(&mut self, board_size: u8) -> Result<(), JsValue> {
+        self.options.set_board_size(board_size).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn set_include_application(&mut self, include: bool) {
+        self.options.include_application = include;
+    }
+
+    pub fn add_property(&mut self, name: &str, value: &str) -> Result<(), JsValue> {
+        self.options.add_property(name, value).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Sets date format to "date", "datetime" or "none"
+    pub fn set_date_format(&mut self, date_format: &str) -> Result<(), JsValue> {
+        self.options.date_format = date_format.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
+    /// Sets the timezone of the date as minutes from UTC, at most 14 hours
+    pub fn set_utc_offset(&mut self, minutes: i32) -> Result<(), JsValue> {
+        self.options.set_utc_offset(minutes).map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[wasm_bindgen]
+pub fn convert_gib_bytes_to_sgf_with_options(bytes: &[u8], options: &JsConversionOptions) -> Option<String> {
+    gib_to_sgf_with_options(&decode_gib(bytes).0, &options.options).ok().map(|c| c.sgf)
+}
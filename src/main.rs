@@ -1,62 +1,513 @@
+mod batch;
+mod cli;
+mod diff;
 mod files;
+mod index;
+mod info;
+mod stats;
+mod watch;
 
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::env;
-use std::path::Path;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use filetime::FileTime;
-use gib2sgf::gib_to_sgf;
-use crate::files::{collect_files, normalize_path};
+use encoding_rs::EUC_KR;
+use gib2sgf::{gib_to_sgf_bytes, gib_to_sgf_with_options, decode_gib, sgf_to_gib_with_warnings, Gib, GibMetadata, SgfCollection, SgfToGibError};
+use crate::cli::{Command, ConvertArgs, VerifyArgs, InfoArgs, InfoFormat, IndexArgs, SearchArgs, StatsArgs, StatsFormat, OutputFormat, Verbosity, STDIO};
+use crate::batch::{Summary, Outcome, FileError, FileResult, Log};
+use crate::diff::diff_collections;
+use crate::index::{read_index, write_index, IndexEntry};
+use crate::info::GameInfo;
+use crate::stats::PlayerStats;
+use crate::files::{is_gib_file, is_sgf_file, normalize_path, output_path, InputFile};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let files = collect_files(Path::new(args.get(1).unwrap_or(&String::from(".")))).unwrap();
-    let mut converted = false;
-    let test = env::var("TEST").is_ok();
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
 
-    for gib_path in files {
-        let sgf_path = normalize_path(&gib_path).with_extension("sgf");
+    let exit_code = match command {
+        Command::Convert(args) => convert(&args),
+        Command::ToGib(args) => convert_to_gib(&args),
+        Command::Watch(args) => watch(&args),
+        Command::Verify(args) => verify(&args),
+        Command::Info(args) => info(&args),
+        Command::Index(args) => index(&args),
+        Command::Search(args) => search(&args),
+        Command::Stats(args) => stats(&args),
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            0
+        }
+    };
 
-        if test {
-            let gib_contents = fs::read_to_string(&gib_path).unwrap();
-            let sgf = gib_to_sgf(&gib_contents).unwrap();
-            if let Ok(existing_sgf) = fs::read_to_string(&sgf_path) {
-                let old = remove_app_version_from_sgf(&existing_sgf);
-                let new = remove_app_version_from_sgf(&sgf);
+    process::exit(exit_code);
+}
+
+/// Converts GIB files to SGF, or JSON
+fn convert(args: &ConvertArgs) -> i32 {
+    if args.is_single() {
+        return convert_single(args, &|bytes, path, log| convert_gib(bytes, path, args, log));
+    }
+
+    let mut summary = Summary::new("converted");
+    let target = |file: &InputFile| output_path(file, args.output_dir.as_deref(), args.format.extension());
+
+    let files = summary.collect_inputs(&args.inputs, &is_gib_file);
+    let sources = target_sources(&files, &target);
+    summary.run(&files, args.jobs, |file, log| convert_file(file, &target(file), &sources, args, log));
+
+    finish(&summary, args.verbosity)
+}
 
-                if old != new {
-                    println!("Results differ on {:?}\n   old: {}\n   new: {}", gib_path, old, new);
+fn convert_file(file: &InputFile, target: &Path, sources: &TargetSources, args: &ConvertArgs, log: &mut Log) -> FileResult {
+    if !should_convert(file, target, sources, args, log) {
+        return Ok(Outcome::Skipped);
+    }
+
+    let output = convert_gib(&fs::read(&file.path)?, &file.path, args, log)?;
+    write_output(Some(&file.path), target, &output, args.dry_run)
+}
+
+fn convert_gib(gib_bytes: &[u8], path: &Path, args: &ConvertArgs, log: &mut Log) -> Result<Vec<u8>, FileError> {
+    let (gib_contents, _) = decode_gib(gib_bytes);
+    let output = match args.format {
+        OutputFormat::Sgf => {
+            let conversion = gib_to_sgf_with_options(&gib_contents, &args.options)?;
+            for warning in &conversion.parse_warnings {
+                log.err(format!("Warning {:?}: {}", path, warning));
+            }
+            for warning in &conversion.warnings {
+                log.err(format!("Warning {:?}: {}", path, warning));
+            }
+            conversion.sgf
+        }
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => gib_to_json(&gib_contents)?,
+    };
+    Ok(output.into_bytes())
+}
+
+/// Converts the GIB files in a directory, and then new files as they are written
+fn watch(args: &ConvertArgs) -> i32 {
+    let dir = &args.inputs[0];
+    if !dir.is_dir() {
+        eprintln!("error: {:?} is not a directory", dir);
+        return 2;
+    }
+
+    // Files saved during the first conversion are picked up by the watcher
+    let scan = || {
+        convert(args);
+        if args.verbosity != Verbosity::Quiet {
+            println!("Watching {:?} for new files", dir);
+        }
+    };
+
+    let sources = TargetSources::new();
+    let result = watch::watch(dir, &scan, &|file| {
+        let target = output_path(&file, args.output_dir.as_deref(), args.format.extension());
+        let mut log = Log::default();
+        let result = convert_file(&file, &target, &sources, args, &mut log);
+        log.print();
+        if let Err(e) = result {
+            eprintln!("Error   {:?}: {}", file.path, e);
+        }
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: can't watch {:?}: {}", dir, e);
+            1
+        }
+    }
+}
+
+/// Converts SGF files to GIB
+fn convert_to_gib(args: &ConvertArgs) -> i32 {
+    if args.is_single() {
+        return convert_single(args, &convert_sgf);
+    }
+
+    let mut summary = Summary::new("converted");
+    let target = |file: &InputFile| output_path(file, args.output_dir.as_deref(), "gib");
+
+    let files = summary.collect_inputs(&args.inputs, &is_sgf_file);
+    let sources = target_sources(&files, &target);
+    summary.run(&files, args.jobs, |file, log| convert_file_to_gib(file, &target(file), &sources, args, log));
+
+    finish(&summary, args.verbosity)
+}
+
+fn convert_file_to_gib(file: &InputFile, target: &Path, sources: &TargetSources, args: &ConvertArgs, log: &mut Log) -> FileResult {
+    if !should_convert(file, target, sources, args, log) {
+        return Ok(Outcome::Skipped);
+    }
+
+    let output = convert_sgf(&fs::read(&file.path)?, &file.path, log)?;
+    write_output(Some(&file.path), target, &output, args.dry_run)
+}
+
+fn convert_sgf(sgf_bytes: &[u8], path: &Path, log: &mut Log) -> Result<Vec<u8>, FileError> {
+    let sgf = std::str::from_utf8(sgf_bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let conversion = sgf_to_gib_with_warnings(sgf)?;
+    for warning in &conversion.warnings {
+        log.err(format!("Warning {:?}: {}", path, warning));
+    }
+    let gib = conversion.gib;
+
+    // Tygem uses Korean code page, but keep UTF-8 for names it can't represent
+    let (bytes, _, unmappable) = EUC_KR.encode(&gib);
+    Ok(if unmappable { gib.into_bytes() } else { bytes.into_owned() })
+}
+
+/// Converts contents of a file, given with its path for messages
+type ConvertFn<'a> = dyn Fn(&[u8], &Path, &mut Log) -> Result<Vec<u8>, FileError> + 'a;
+
+/// Converts a single file or stdin to the output file given with `--output`, or to stdout
+fn convert_single(args: &ConvertArgs, convert: &ConvertFn) -> i32 {
+    let mut summary = Summary::new("converted");
+    let input = &args.inputs[0];
+    let mut log = Log::default();
+
+    let result = read_single_input(input).and_then(|bytes| {
+        let output = convert(&bytes, input, &mut log)?;
+        match args.output.as_deref() {
+            Some(path) if path != Path::new(STDIO) => {
+                if args.verbosity == Verbosity::Verbose {
+                    eprintln!("Convert {:?} -> {:?}", input, path);
                 }
+                // Stdin has no modification time to copy
+                let source = Some(input.as_path()).filter(|&i| i != Path::new(STDIO));
+                write_output(source, path, &output, args.dry_run)?;
+            }
+            _ => if !args.dry_run {
+                io::stdout().write_all(&output)?;
             }
+        }
+        Ok(Outcome::Done)
+    });
+
+    log.print();
+    summary.record(input, result);
+    summary.exit_code()
+}
+
+fn read_single_input(input: &Path) -> Result<Vec<u8>, FileError> {
+    let mut bytes = Vec::new();
+    if input == Path::new(STDIO) {
+        io::stdin().read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            return Err(FileError::InvalidInput("stdin is empty".to_string()));
+        }
+    } else if input.is_file() {
+        bytes = fs::read(input)?;
+    } else {
+        return Err(FileError::InvalidInput("--output requires a single input file".to_string()));
+    }
+    Ok(bytes)
+}
+
+/// The first file converted to each target
+type TargetSources<'a> = HashMap<PathBuf, &'a Path>;
 
-            // TODO: check that output is equal to previously generated SGF file
-        } else if !sgf_path.exists() {
-            println!("Convert {:?} -> {:?}", gib_path, sgf_path);
+/// Finds which file is converted to each target, so that parallel conversions never
+/// write to the same file when eg. `--output-dir` maps two inputs to the same target
+fn target_sources<'a>(files: &'a [InputFile], target: &dyn Fn(&InputFile) -> PathBuf) -> TargetSources<'a> {
+    let mut sources = HashMap::new();
+    for file in files {
+        sources.entry(target(file)).or_insert(file.path.as_path());
+    }
+    sources
+}
 
-            // Convert file
-            let gib_contents = fs::read_to_string(&gib_path).unwrap();
-            let sgf_contents = gib_to_sgf(&gib_contents).unwrap();
-            fs::write(&sgf_path, sgf_contents).unwrap();
+/// Reports the conversion and returns true unless the target already exists or
+/// belongs to an earlier file
+fn should_convert(file: &InputFile, target: &Path, sources: &TargetSources, args: &ConvertArgs, log: &mut Log) -> bool {
+    if sources.get(target).is_some_and(|&source| source != file.path) {
+        if args.verbosity == Verbosity::Verbose {
+            log.out(format!("Skip    {:?} -> {:?} (same output as {:?})", file.path, target, sources[target]));
+        }
+        return false;
+    }
 
-            // Set the same modification time as the original file
-            let metadata = fs::metadata(&gib_path).unwrap();
-            let mtime = FileTime::from_last_modification_time(&metadata);
-            filetime::set_file_mtime(&sgf_path, mtime).unwrap();
-            converted = true
+    if target.exists() && !args.force && !(args.update && is_outdated(target, &file.path)) {
+        if args.verbosity == Verbosity::Verbose {
+            log.out(format!("Skip    {:?} -> {:?}", file.path, target));
         }
+        return false;
+    }
+
+    if args.verbosity != Verbosity::Quiet {
+        log.out(format!("Convert {:?} -> {:?}", file.path, target));
+    }
+    true
+}
+
+/// True if the source has been modified after the target, which gets the modification time
+/// of its source when written
+fn is_outdated(target: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(target), modified(source)) {
+        (Some(target), Some(source)) => source > target,
+        _ => false
+    }
+}
+
+/// Writes the converted file, setting the same modification time as the source file if given
+fn write_output(source: Option<&Path>, target: &Path, contents: &[u8], dry_run: bool) -> FileResult {
+    if dry_run {
+        return Ok(Outcome::Done);
     }
 
-    if !test && !converted {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(target, contents)?;
+
+    if let Some(source) = source {
+        let metadata = fs::metadata(source)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        filetime::set_file_mtime(target, mtime)?;
+    }
+    Ok(Outcome::Done)
+}
+
+/// Prints the summary and returns the exit code for the command
+fn finish(summary: &Summary, verbosity: Verbosity) -> i32 {
+    if summary.done() == 0 && summary.exit_code() == 0 && verbosity != Verbosity::Quiet {
         println!("no unconverted files")
+    } else {
+        summary.print(verbosity);
+    }
+    summary.exit_code()
+}
+
+#[cfg(feature = "serde")]
+fn gib_to_json(gib_contents: &str) -> Result<String, FileError> {
+    let gib = Gib::parse(gib_contents)?;
+    let json = serde_json::json!({
+        "metadata": gib.get_metadata(),
+        "moves": gib.get_moves(),
+    });
+    Ok(serde_json::to_string_pretty(&json).expect("game model serializes to JSON"))
+}
+
+/// Number of differences shown for each file
+const MAX_DIFFERENCES: usize = 20;
+
+/// Checks that existing SGF files are equal to what the current version would produce
+fn verify(args: &VerifyArgs) -> i32 {
+    let mut summary = Summary::new("verified");
+
+    let files = summary.collect_inputs(&args.inputs, &is_gib_file);
+    summary.run(&files, args.jobs, |file, log| verify_file(file, args, log));
+
+    summary.print(args.verbosity);
+    summary.exit_code()
+}
+
+fn verify_file(file: &InputFile, args: &VerifyArgs, log: &mut Log) -> FileResult {
+    let sgf_path = normalize_path(&file.path).with_extension("sgf");
+    let existing_sgf = match fs::read_to_string(&sgf_path) {
+        Ok(sgf) => sgf,
+        Err(_) => {
+            if args.verbosity == Verbosity::Verbose {
+                log.out(format!("Missing {:?}", sgf_path));
+            }
+            return Ok(Outcome::Skipped);
+        }
+    };
+
+    let old = SgfCollection::parse(&existing_sgf)?;
+    let new = SgfCollection::parse(&gib_to_sgf_bytes(&fs::read(&file.path)?)?)?;
+
+    let differences = diff_collections(&old, &new);
+    if !differences.is_empty() {
+        log.err(format!("Differ  {:?} -> {:?}", file.path, sgf_path));
+        for difference in differences.iter().take(MAX_DIFFERENCES) {
+            log.err(format!("    {}", difference));
+        }
+        if differences.len() > MAX_DIFFERENCES {
+            log.err(format!("    ... and {} more", differences.len() - MAX_DIFFERENCES));
+        }
+        return Err(FileError::Mismatch(format!("{} differences to {:?}", differences.len(), sgf_path)));
+    }
+
+    if args.verbosity == Verbosity::Verbose {
+        log.out(format!("OK      {:?}", file.path));
+    }
+    Ok(Outcome::Done)
+}
+
+/// Prints the game information of GIB files
+fn info(args: &InfoArgs) -> i32 {
+    let mut summary = Summary::new("printed");
+    #[cfg(feature = "serde")]
+    let mut games = Vec::new();
+
+    if args.format == InfoFormat::Tsv {
+        println!("{}", GameInfo::tsv_header());
+    }
+
+    for file in summary.collect_inputs(&args.inputs, &is_gib_file) {
+        let result = read_info(&file).map(|game| {
+            match args.format {
+                InfoFormat::Table => print!("{}", game.to_table()),
+                InfoFormat::Tsv => println!("{}", game.to_tsv()),
+                #[cfg(feature = "serde")]
+                InfoFormat::Json => games.push(game),
+            }
+            Outcome::Done
+        });
+        summary.record(&file.path, result);
+    }
+
+    #[cfg(feature = "serde")]
+    if args.format == InfoFormat::Json {
+        print_info_json(&games);
+    }
+    if summary.exit_code() != 0 {
+        summary.print(Verbosity::Quiet);
+    }
+    summary.exit_code()
+}
+
+fn read_info(file: &InputFile) -> Result<GameInfo, FileError> {
+    let gib_bytes = fs::read(&file.path)?;
+    let gib = Gib::parse(&decode_gib(&gib_bytes).0)?;
+    Ok(GameInfo::new(file.path.clone(), &gib))
+}
+
+#[cfg(feature = "serde")]
+fn print_info_json(games: &[GameInfo]) {
+    let json: Vec<_> = games.iter().map(|g| g.to_json()).collect();
+    println!("{}", serde_json::to_string_pretty(&json).expect("game information serializes to JSON"));
+}
+
+/// Writes the metadata of GIB and SGF files to an index
+fn index(args: &IndexArgs) -> i32 {
+    let mut summary = Summary::new("indexed");
+    let entries = read_archive(&args.inputs, &mut summary, args.verbosity);
+
+    if let Err(e) = fs::write(&args.output, write_index(&entries)) {
+        eprintln!("error: can't write {:?}: {}", args.output, e);
+        return 1;
+    }
+    summary.print(args.verbosity);
+    summary.exit_code()
+}
+
+/// Reads the metadata of GIB and SGF files, skipping SGF files converted from GIB files
+/// of the same archive so that each game is included once
+fn read_archive(inputs: &[PathBuf], summary: &mut Summary, verbosity: Verbosity) -> Vec<IndexEntry> {
+    let files = summary.collect_inputs(inputs, &|name| is_gib_file(name) || is_sgf_file(name));
+    let converted: HashSet<PathBuf> = files.iter()
+        .filter(|file| file.path.to_str().is_some_and(is_gib_file))
+        .map(|file| normalize_path(&file.path).with_extension("sgf"))
+        .collect();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let result = if converted.contains(&file.path) {
+            if verbosity == Verbosity::Verbose {
+                println!("Skip    {:?} (converted from a GIB file)", file.path);
+            }
+            Ok(Outcome::Skipped)
+        } else {
+            read_index_entry(&file).map(|entry| {
+                entries.push(entry);
+                Outcome::Done
+            })
+        };
+        summary.record(&file.path, result);
+    }
+    entries
+}
+
+fn read_index_entry(file: &InputFile) -> Result<IndexEntry, FileError> {
+    let bytes = fs::read(&file.path)?;
+    if file.path.to_str().is_some_and(is_gib_file) {
+        let gib = Gib::parse(&decode_gib(&bytes).0)?;
+        return Ok(IndexEntry::new(file.path.clone(), gib.get_metadata(), gib.get_moves().len()));
+    }
+
+    // SGF files are read directly, since converting them to GIB is lossy
+    let sgf = String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let collection = SgfCollection::parse(&sgf)?;
+    let nodes = collection.main_line();
+    let root = nodes.first().ok_or(SgfToGibError::NoGame)?;
+    let moves = nodes.iter().filter(|n| n.get_property("B").is_some() || n.get_property("W").is_some()).count();
+    Ok(IndexEntry::new(file.path.clone(), GibMetadata::from_sgf(root), moves))
+}
+
+/// Prints the games of the index that match the search
+fn search(args: &SearchArgs) -> i32 {
+    match load_index(&args.index) {
+        Ok(entries) => {
+            for entry in entries.iter().filter(|e| args.filter.matches(e)) {
+                println!("{}", entry);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
     }
 }
 
-fn remove_app_version_from_sgf(str: &str) -> String {
-    if let Some(start) = str.find("AP[gib2sgf:") {
-        let suffix = &str[start..];
-        if let Some(end) = suffix.find("]") {
-            return format!("{}{}", &str[..start], &suffix[end + 1..]);
+fn load_index(path: &Path) -> Result<Vec<IndexEntry>, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|csv| read_index(&csv))
+        .map_err(|e| format!("can't read index {:?}: {}", path, e))
+}
+
+/// Prints the statistics of a player, from the index or from the files
+fn stats(args: &StatsArgs) -> i32 {
+    let mut summary = Summary::new("read");
+    let entries = match &args.index {
+        Some(index) => match load_index(index) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return 1;
+            }
+        },
+        None => read_archive(&args.inputs, &mut summary, Verbosity::Normal),
+    };
+
+    match PlayerStats::new(&args.player, &entries) {
+        Some(stats) => match args.format {
+            StatsFormat::Text => print!("{}", stats.to_text()),
+            #[cfg(feature = "serde")]
+            StatsFormat::Json => print_stats_json(&stats),
+        },
+        None => {
+            eprintln!("error: no games found for {}", args.player);
+            return 1;
         }
     }
-    return str.to_string();
+
+    if summary.exit_code() != 0 {
+        summary.print(Verbosity::Quiet);
+    }
+    summary.exit_code()
+}
+
+#[cfg(feature = "serde")]
+fn print_stats_json(stats: &PlayerStats) {
+    println!("{}", serde_json::to_string_pretty(&stats.to_json()).expect("statistics serialize to JSON"));
 }
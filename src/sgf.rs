@@ -1,25 +1,53 @@
 use linked_hash_map::LinkedHashMap;
-use crate::go::{PlayerColor, BoardCoordinate, Score, GameResult, Handicap};
-use crate::time::LocalDate;
+use crate::go::{PlayerColor, BoardCoordinate, Score, GameResult, Handicap, ByoYomi, TimeLeft};
+use crate::time::{LocalDate, LocalDateTime};
 
+#[derive(Default)]
 pub struct SgfCollection {
     trees: Vec<SgfTree>
 }
 
+#[derive(Default)]
 pub struct SgfTree {
     nodes: Vec<SgfNode>,
     children: Vec<SgfTree>,
 }
 
+#[derive(Default)]
 pub struct SgfNode {
     properties: LinkedHashMap<String, Vec<String>>
 }
 
 impl SgfCollection {
+    pub fn new() -> SgfCollection {
+        SgfCollection { trees: Vec::new() }
+    }
+
     pub fn from_game(tree: SgfTree) -> SgfCollection {
         SgfCollection { trees: vec![tree] }
     }
 
+    pub fn add_tree(&mut self, tree: SgfTree) {
+        self.trees.push(tree)
+    }
+
+    pub fn trees(&self) -> &Vec<SgfTree> {
+        &self.trees
+    }
+
+    /// Returns the nodes of the first game, following the first variation at each branch
+    pub fn main_line(&self) -> Vec<&SgfNode> {
+        let mut nodes = Vec::new();
+        let mut tree = self.trees.first();
+
+        while let Some(t) = tree {
+            nodes.extend(t.nodes.iter());
+            tree = t.children.first();
+        }
+
+        nodes
+    }
+
     pub fn to_sgf(&self) -> String {
         let mut result = String::new();
 
@@ -36,12 +64,39 @@ impl SgfTree {
         SgfTree { nodes: Vec::new(), children: Vec::new() }
     }
 
+    pub fn from_node(node: SgfNode) -> SgfTree {
+        SgfTree { nodes: vec![node], children: Vec::new() }
+    }
+
     pub fn add_node(&mut self, node: SgfNode) {
         self.nodes.push(node)
     }
 
-    pub fn add_move(&mut self, color: PlayerColor, coordinate: BoardCoordinate) {
-        self.nodes.push(SgfNode::new_move(color, coordinate))
+    /// Adds a variation starting after the last node of this tree
+    pub fn add_child(&mut self, child: SgfTree) {
+        self.children.push(child)
+    }
+
+    pub fn nodes(&self) -> &Vec<SgfNode> {
+        &self.nodes
+    }
+
+    pub fn children(&self) -> &Vec<SgfTree> {
+        &self.children
+    }
+
+    /// Adds a move node, returning it so that further properties can be attached to it
+    pub fn add_move(&mut self, color: PlayerColor, coordinate: BoardCoordinate) -> &mut SgfNode {
+        self.push_node(SgfNode::new_move(color, coordinate))
+    }
+
+    pub fn add_pass(&mut self, color: PlayerColor) -> &mut SgfNode {
+        self.push_node(SgfNode::new_pass(color))
+    }
+
+    fn push_node(&mut self, node: SgfNode) -> &mut SgfNode {
+        self.nodes.push(node);
+        self.nodes.last_mut().unwrap()
     }
 
     fn to_sgf(&self, result: &mut String) {
@@ -51,10 +106,12 @@ impl SgfTree {
             result.push(';');
 
             for (name, values) in &node.properties {
+                let value_type = ValueType::of_property(name);
+
                 result.push_str(name);
                 for value in values {
                     result.push('[');
-                    result.push_str(value);
+                    value_type.write_escaped(value, result);
                     result.push(']');
                 }
             }
@@ -79,6 +136,19 @@ impl SgfNode {
         node
     }
 
+    /// In FF[4] a pass is represented as a move with an empty value
+    pub fn new_pass(color: PlayerColor) -> SgfNode {
+        let mut node = SgfNode::new();
+        node.set_property(color.pick("B", "W"), "");
+        node
+    }
+
+    /// Records player's clock after a move using `BL`/`WL` and `OB`/`OW` properties
+    pub fn set_time_left(&mut self, color: PlayerColor, time_left: TimeLeft) {
+        self.set_property(color.pick("BL", "WL"), time_left.seconds());
+        self.set_property_maybe(color.pick("OB", "OW"), time_left.periods());
+    }
+
     pub fn set_property(&mut self, name: &str, value: impl ToSgf) {
         self.properties.insert(name.to_string(), vec![value.to_sgf()]);
     }
@@ -92,8 +162,79 @@ impl SgfNode {
             self.set_property(name, v)
         }
     }
+
+    /// Returns the first value of given property
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name)?.first().map(|s| s.as_str())
+    }
+
+    pub fn get_property_list(&self, name: &str) -> Option<&Vec<String>> {
+        self.properties.get(name)
+    }
+
+    /// Iterates over all properties in insertion order
+    pub fn properties(&self) -> impl Iterator<Item=(&String, &Vec<String>)> {
+        self.properties.iter()
+    }
 }
 
+/// Value types of FF[4] that affect how values need to be escaped
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ValueType {
+    /// Formatted text where line breaks are preserved
+    Text,
+    /// Single line text, line breaks are converted to spaces
+    SimpleText,
+    /// Two values separated by ':', eg. `AP[gib2sgf:0.1.2]` or `LB[dd:A]`
+    Compose,
+    /// Numbers, points, colors and unknown properties
+    Other,
+}
+
+impl ValueType {
+    fn of_property(name: &str) -> ValueType {
+        match name {
+            "C" | "GC" => ValueType::Text,
+            "AN" | "BR" | "BT" | "CA" | "CP" | "DT" | "EV" | "GN" | "N" | "ON" | "OT" | "PB" | "PC" | "PW" |
+            "RE" | "RO" | "RU" | "SO" | "US" | "WR" | "WT" => ValueType::SimpleText,
+            "AP" | "AR" | "FG" | "LB" | "LN" | "SZ" => ValueType::Compose,
+            _ => ValueType::Other
+        }
+    }
+
+    fn write_escaped(&self, value: &str, result: &mut String) {
+        match self {
+            ValueType::Text | ValueType::Other => escape_into(value, false, result),
+            ValueType::SimpleText => escape_into(&remove_line_breaks(value), false, result),
+            ValueType::Compose => {
+                // The first ':' separates the parts, any others belong to the second part
+                if let Some((first, second)) = value.split_once(':') {
+                    escape_into(&remove_line_breaks(first), true, result);
+                    result.push(':');
+                    escape_into(&remove_line_breaks(second), true, result);
+                } else {
+                    escape_into(&remove_line_breaks(value), true, result);
+                }
+            }
+        }
+    }
+}
+
+fn escape_into(value: &str, escape_colon: bool, result: &mut String) {
+    for c in value.chars() {
+        if c == ']' || c == '\\' || (escape_colon && c == ':') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+}
+
+fn remove_line_breaks(value: &str) -> String {
+    value.replace("\r\n", " ").replace(['\n', '\r'], " ")
+}
+
+/// Converts values to SGF property values. The values are not escaped: escaping depends
+/// on the type of the property and is done when the whole tree is written.
 pub trait ToSgf {
     fn to_sgf(&self) -> String;
 }
@@ -104,6 +245,24 @@ impl ToSgf for &str {
     }
 }
 
+impl ToSgf for String {
+    fn to_sgf(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ToSgf for u8 {
+    fn to_sgf(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToSgf for u32 {
+    fn to_sgf(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl ToSgf for Score {
     fn to_sgf(&self) -> String {
         self.to_string()
@@ -116,6 +275,12 @@ impl ToSgf for LocalDate {
     }
 }
 
+impl ToSgf for LocalDateTime {
+    fn to_sgf(&self) -> String {
+        self.iso_string()
+    }
+}
+
 impl ToSgf for BoardCoordinate {
     fn to_sgf(&self) -> String {
         let x = (b'a' + self.x) as char;
@@ -153,6 +318,13 @@ impl ToSgf for Handicap {
     }
 }
 
+/// Overtime description in the format most SGF editors recognize, eg. `3x30 byo-yomi`
+impl ToSgf for ByoYomi {
+    fn to_sgf(&self) -> String {
+        format!("{}x{} byo-yomi", self.periods(), self.period_time())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,6 +355,50 @@ mod tests {
         assert_eq!("(;PB[Honinbo Shusaku]BR[7P]PW[Go Seigen]WR[9P]RE[B+37.5]KM[6.5]DT[2020-02-29]FF[4]GM[1]CA[UTF-8]SZ[19])", collection.to_sgf());
     }
 
+    #[test]
+    fn test_passes() {
+        let mut tree = SgfTree::new();
+        tree.add_node(SgfNode::new());
+        tree.add_move(PlayerColor::Black, BoardCoordinate::new(3, 3));
+        tree.add_pass(PlayerColor::White);
+        tree.add_pass(PlayerColor::Black);
+
+        assert_eq!("(;;B[dd];W[];B[])", SgfCollection::from_game(tree).to_sgf());
+    }
+
+    #[test]
+    fn test_time_left() {
+        let mut tree = SgfTree::new();
+        tree.add_move(PlayerColor::Black, BoardCoordinate::new(3, 3)).set_time_left(PlayerColor::Black, TimeLeft::new(587, None));
+        tree.add_move(PlayerColor::White, BoardCoordinate::new(15, 15)).set_time_left(PlayerColor::White, TimeLeft::new(25, Some(2)));
+        tree.add_pass(PlayerColor::Black).set_time_left(PlayerColor::Black, TimeLeft::new(30, Some(3)));
+
+        assert_eq!("(;B[dd]BL[587];W[pp]WL[25]OW[2];B[]BL[30]OB[3])", SgfCollection::from_game(tree).to_sgf());
+    }
+
+    #[test]
+    fn test_escaping() {
+        let mut root = SgfNode::new();
+        root.set_property("PB", "[Team] Foo");
+        root.set_property("PW", "back\\slash");
+        root.set_property("GN", "multi\nline\r\nname");
+        root.set_property("C", "Comment with ] and \\\nsecond line");
+        root.set_property("AP", "gib2sgf:0.1.2");
+        root.set_property_list("LB", vec!["dd:A]", "pp:a:b"]);
+        root.set_property("XX", "custom]");
+
+        let sgf = SgfCollection::from_game(SgfTree::from_node(root)).to_sgf();
+        assert_eq!(sgf, "(;PB[[Team\\] Foo]PW[back\\\\slash]GN[multi line name]C[Comment with \\] and \\\\\nsecond line]\
+                         AP[gib2sgf:0.1.2]LB[dd:A\\]][pp:a\\:b]XX[custom\\]])");
+
+        let parsed = SgfCollection::parse(&sgf).unwrap();
+        let node = &parsed.trees()[0].nodes()[0];
+        assert_eq!(node.get_property("PB"), Some("[Team] Foo"));
+        assert_eq!(node.get_property("PW"), Some("back\\slash"));
+        assert_eq!(node.get_property("C"), Some("Comment with ] and \\\nsecond line"));
+        assert_eq!(node.get_property_list("LB"), Some(&vec!["dd:A]".to_string(), "pp:a:b".to_string()]));
+    }
+
     #[test]
     fn formatting_game_results() {
         assert_eq!(GameResult::Jigo.to_sgf(), "0");
@@ -194,6 +410,12 @@ mod tests {
         assert_eq!(GameResult::Forfeit(PlayerColor::White).to_sgf(), "W+F");
     }
 
+    #[test]
+    fn formatting_byo_yomi() {
+        assert_eq!(ByoYomi::from(3, 30).unwrap().to_sgf(), "3x30 byo-yomi");
+        assert_eq!(ByoYomi::from(5, 60).unwrap().to_sgf(), "5x60 byo-yomi");
+    }
+
     #[test]
     fn board_coordinates() {
         assert_eq!(BoardCoordinate { x: 0, y: 0 }.to_sgf(), "aa");
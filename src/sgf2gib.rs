@@ -0,0 +1,553 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use crate::sgf::{SgfCollection, SgfNode};
+use crate::sgf_parser::SgfParseError;
+use crate::go::{PlayerColor, BoardCoordinate, GameResult, Score, Handicap, ByoYomi, TimeControl, TimeLeft};
+use crate::gib::GibMetadata;
+use crate::time::LocalDate;
+
+#[derive(Debug)]
+pub enum SgfToGibError {
+    Parse(SgfParseError),
+    /// The collection does not contain any games
+    NoGame,
+    InvalidProperty { name: String, value: String },
+    /// A valid property that GIB can't represent, reported as a warning
+    Unsupported { name: String, value: String },
+}
+
+/// Result of a conversion to GIB, with the properties that had to be left out
+#[derive(Debug)]
+pub struct GibConversion {
+    pub gib: String,
+    pub warnings: Vec<SgfToGibError>,
+}
+
+type SgfToGibResult<T> = Result<T, SgfToGibError>;
+
+///
+/// Converts an SGF file to Tygem GIB format. Only the main line of the first game
+/// in the file is converted since GIB files can't represent variations.
+///
+pub fn sgf_to_gib(sgf_data: &str) -> SgfToGibResult<String> {
+    sgf_to_gib_with_warnings(sgf_data).map(|c| c.gib)
+}
+
+/// Converts an SGF file to GIB like `sgf_to_gib`, also returning the properties that were left out
+pub fn sgf_to_gib_with_warnings(sgf_data: &str) -> SgfToGibResult<GibConversion> {
+    let collection = SgfCollection::parse(sgf_data).map_err(SgfToGibError::Parse)?;
+    collection_to_gib_with_warnings(&collection)
+}
+
+/// Converts the main line of the first game of given collection to GIB
+pub fn collection_to_gib(collection: &SgfCollection) -> SgfToGibResult<String> {
+    collection_to_gib_with_warnings(collection).map(|c| c.gib)
+}
+
+/// Like `collection_to_gib`, but also returns the properties that were left out
+pub fn collection_to_gib_with_warnings(collection: &SgfCollection) -> SgfToGibResult<GibConversion> {
+    let nodes = collection.main_line();
+    let root = *nodes.first().ok_or(SgfToGibError::NoGame)?;
+
+    let board_size = match root.get_property("SZ") {
+        Some(size) => size.parse::<u8>().map_err(|_| invalid_property("SZ", size))?,
+        None => 19
+    };
+
+    let mut warnings = Vec::new();
+    let mut lines = vec![String::from(r"\HS")];
+    let mut attribute = |name: &str, value: &str| lines.push(format!(r"\[{}={}\]", name, value));
+
+    for &color in [PlayerColor::Black, PlayerColor::White].iter() {
+        if let Some(nick) = root.get_property(color.pick("PB", "PW")) {
+            let name = match root.get_property(color.pick("BR", "WR")) {
+                Some(rank) => format!("{} ({})", nick, rank),
+                None => nick.to_string()
+            };
+            attribute(color.pick("GAMEBLACKNAME", "GAMEWHITENAME"), &name);
+        }
+    }
+
+    let komi = root.get_property("KM").map(|km| Score::from_sgf(km).ok_or_else(|| invalid_property("KM", km))).transpose()?;
+    if let Some(komi) = komi {
+        attribute("GAMEGONGJE", &komi.tenths().to_string());
+    }
+
+    // GIB needs a full date, so partial dates like `2020-04` are left out
+    if let Some(dt) = root.get_property("DT") {
+        match LocalDate::from_sgf(dt) {
+            Some(date) => attribute("GAMEDATE", &format!("{:04}-{:>2}-{:>2}- 0- 0- 0", date.year(), date.month(), date.day())),
+            None => warnings.push(unsupported("DT", dt)),
+        }
+    }
+
+    if let Some(place) = root.get_property("SO").or_else(|| root.get_property("PC")) {
+        attribute("GAMEPLACE", place);
+    }
+
+    if board_size != 19 {
+        attribute("GAMEBOARDSIZE", &board_size.to_string());
+    }
+
+    attribute("GAMEINFOMAIN", &game_info_main(root, komi, &mut warnings)?);
+    lines.push(String::from(r"\HE"));
+    lines.push(String::from(r"\GS"));
+
+    let handicap_points = root.get_property_list("AB").map(|points| {
+        points.iter().map(|p| parse_point(p, board_size)?.ok_or_else(|| invalid_property("AB", p))).collect::<SgfToGibResult<Vec<_>>>()
+    }).transpose()?.unwrap_or_default();
+
+    let stones = u8::try_from(handicap_points.len()).map_err(|_| invalid_property("AB", &format!("{} points", handicap_points.len())))?;
+    let handicap = match root.get_property("HA") {
+        Some(ha) => {
+            let ha = ha.parse::<u8>().map_err(|_| invalid_property("HA", ha))?;
+            // All the stones are written, so a handicap that doesn't match them is left out
+            if stones > 0 && ha != stones {
+                warnings.push(unsupported("HA", &ha.to_string()));
+                stones
+            } else {
+                ha
+            }
+        }
+        None => stones
+    };
+    lines.push(format!("INI 0 1 {} &4", if Handicap::from(handicap).is_some() { handicap } else { 0 }));
+
+    // GIB has no setup stones besides the handicap, which needs at least two black stones
+    for (i, node) in nodes.iter().enumerate() {
+        for &name in ["AB", "AW", "AE"].iter() {
+            if let Some(points) = node.get_property_list(name) {
+                if i > 0 || name != "AB" || Handicap::from(handicap).is_none() {
+                    warnings.push(unsupported(name, &points.join("][")));
+                }
+            }
+        }
+    }
+
+    let mut move_number = 2;
+
+    // Stones that are not in the fixed placement are written as black moves before white's first move
+    if let Some(handicap) = Handicap::from(handicap) {
+        let mut fixed = match handicap.handicap_points(board_size) {
+            Ok(points) => points,
+            // Stones that don't fit the fixed placement can still be written as placed freely
+            Err(_) if !handicap_points.is_empty() => Vec::new(),
+            Err(_) => return Err(invalid_property("HA", &handicap.stones().to_string())),
+        };
+        let mut points = handicap_points.clone();
+        fixed.sort_by_key(|p| (p.x, p.y));
+        points.sort_by_key(|p| (p.x, p.y));
+
+        if fixed != points {
+            for point in &handicap_points {
+                lines.push(format!("STO 0 {} 1 {} {}", move_number, point.x, point.y));
+                move_number += 1;
+            }
+        }
+    }
+
+    for node in nodes {
+        for &color in [PlayerColor::Black, PlayerColor::White].iter() {
+            let name = color.pick("B", "W");
+            if let Some(value) = node.get_property(name) {
+                let time_left = time_left(node, color)?;
+                let clock = match time_left {
+                    Some(t) => match t.periods() {
+                        Some(periods) => format!(" {} {}", t.seconds(), periods),
+                        None => format!(" {}", t.seconds())
+                    }
+                    None => String::new()
+                };
+
+                match parse_point(value, board_size)? {
+                    Some(point) => lines.push(format!("STO 0 {} {} {} {}{}", move_number, color.pick(1, 2), point.x, point.y, clock)),
+                    None => lines.push(format!("SKI 0 {}", move_number)),
+                }
+                move_number += 1;
+            }
+        }
+    }
+
+    lines.push(String::from(r"\GE"));
+
+    let mut gib = lines.join("\r\n");
+    gib.push_str("\r\n");
+    Ok(GibConversion { gib, warnings })
+}
+
+/// Builds the `GAMEINFOMAIN` attribute with time settings, result and komi
+fn game_info_main(root: &SgfNode, komi: Option<Score>, warnings: &mut Vec<SgfToGibError>) -> SgfToGibResult<String> {
+    let mut info = Vec::new();
+
+    if let Some(tm) = root.get_property("TM") {
+        let main_time = tm.parse::<f32>().map_err(|_| invalid_property("TM", tm))? as u32;
+        // Settings that gib2sgf couldn't interpret are written back as they were
+        let raw = root.get_property("OT").and_then(|ot| ot.strip_prefix("GTIME "))
+            .filter(|gtime| gtime.starts_with(&main_time.to_string()) && !gtime.contains([',', '\\']));
+        match raw {
+            Some(gtime) => info.push(format!("GTIME:{}", gtime)),
+            None => info.push(format!("GTIME:{}", TimeControl::from_sgf(main_time, root.get_property("OT")).to_gib())),
+        }
+    }
+
+    // Unknown results and games without a result are written without GRLT
+    let re = root.get_property("RE").filter(|re| !matches!(re.trim(), "" | "?") && !re.trim().eq_ignore_ascii_case("void"));
+    if let Some(re) = re {
+        let result = GameResult::from_sgf(re).ok_or_else(|| invalid_property("RE", re))?;
+        let grlt = match result {
+            GameResult::Count(winner, _) => Some(winner.pick(0, 1)),
+            GameResult::Resign(winner) => Some(winner.pick(3, 4)),
+            GameResult::Time(winner) => Some(winner.pick(7, 8)),
+            // There are no known GRLT codes for these
+            GameResult::Jigo | GameResult::Forfeit(_) => {
+                warnings.push(unsupported("RE", re));
+                None
+            }
+        };
+        let zipsu = match result {
+            GameResult::Count(_, Some(score)) => score.tenths(),
+            _ => 0
+        };
+
+        if let Some(grlt) = grlt {
+            info.push(format!("GRLT:{}", grlt));
+            info.push(format!("ZIPSU:{}", zipsu));
+        }
+    }
+
+    if let Some(komi) = komi {
+        info.push(format!("GONGJE:{}", komi.tenths()));
+    }
+
+    Ok(info.join(","))
+}
+
+fn time_left(node: &SgfNode, color: PlayerColor) -> SgfToGibResult<Option<TimeLeft>> {
+    let (time_name, periods_name) = color.pick(("BL", "OB"), ("WL", "OW"));
+
+    let seconds = match node.get_property(time_name) {
+        Some(value) => value.parse::<f32>().map_err(|_| invalid_property(time_name, value))? as u32,
+        None => return Ok(None)
+    };
+    let periods = node.get_property(periods_name)
+        .map(|value| value.parse::<u32>().map_err(|_| invalid_property(periods_name, value)))
+        .transpose()?;
+
+    Ok(Some(TimeLeft::new(seconds, periods)))
+}
+
+/// Parses an SGF point, returning None for passes
+fn parse_point(value: &str, board_size: u8) -> SgfToGibResult<Option<BoardCoordinate>> {
+    let bytes = value.as_bytes();
+    if value.is_empty() || (value == "tt" && board_size <= 19) {
+        return Ok(None);
+    }
+
+    if bytes.len() == 2 && bytes.iter().all(|b| b.is_ascii_lowercase()) {
+        let coordinate = BoardCoordinate::new(bytes[0] - b'a', bytes[1] - b'a');
+        if coordinate.x < board_size && coordinate.y < board_size {
+            return Ok(Some(coordinate));
+        }
+    }
+
+    Err(invalid_property("point", value))
+}
+
+impl GibMetadata {
+    /// Reads the game information from the root node of an SGF game, like `Gib::get_metadata`.
+    /// Values that can't be parsed are left out.
+    pub fn from_sgf(root: &SgfNode) -> GibMetadata {
+        let owned = |name: &str| root.get_property(name).map(|s| s.to_string());
+        let time_control = root.get_property("TM").and_then(|tm| tm.parse::<f32>().ok())
+            .map(|main_time| TimeControl::from_sgf(main_time as u32, root.get_property("OT")));
+
+        GibMetadata {
+            black_nick: owned("PB"),
+            black_rank: owned("BR"),
+            white_nick: owned("PW"),
+            white_rank: owned("WR"),
+            komi: root.get_property("KM").and_then(Score::from_sgf),
+            result: root.get_property("RE").and_then(GameResult::from_sgf),
+            date: root.get_property("DT").and_then(LocalDate::from_sgf),
+            place: owned("SO").or_else(|| owned("PC")),
+            board_size: root.get_property("SZ").and_then(|sz| sz.parse().ok()).unwrap_or(19),
+            handicap: root.get_property("HA").and_then(|ha| ha.parse().ok()).and_then(Handicap::from),
+            time_control,
+        }
+    }
+}
+
+fn invalid_property(name: &str, value: &str) -> SgfToGibError {
+    SgfToGibError::InvalidProperty { name: name.to_string(), value: value.to_string() }
+}
+
+fn unsupported(name: &str, value: &str) -> SgfToGibError {
+    SgfToGibError::Unsupported { name: name.to_string(), value: value.to_string() }
+}
+
+impl Score {
+    fn from_sgf(score: &str) -> Option<Score> {
+        score.trim().parse::<f32>().ok().map(Score::new)
+    }
+}
+
+impl LocalDate {
+    /// Parses the first date of `DT` property, which may contain a list of dates
+    fn from_sgf(dt: &str) -> Option<LocalDate> {
+        LocalDate::parse_iso(dt.get(..10)?).ok()
+    }
+}
+
+impl TimeControl {
+    /// Reads overtime of form `3x30 byo-yomi` or `Fischer +10s`, ignoring other descriptions
+    fn from_sgf(main_time: u32, ot: Option<&str>) -> TimeControl {
+        let increment = ot.and_then(|ot| ot.strip_prefix("Fischer +")?.strip_suffix('s')?.parse().ok());
+        match (ot.and_then(ByoYomi::from_sgf), increment) {
+            (None, Some(increment)) => TimeControl::with_increment(main_time, increment),
+            (byo_yomi, _) => TimeControl::new(main_time, byo_yomi),
+        }
+    }
+}
+
+impl ByoYomi {
+    /// Parses overtime descriptions of form `3x30 byo-yomi`
+    fn from_sgf(ot: &str) -> Option<ByoYomi> {
+        let settings = ot.split_whitespace().next()?;
+        let (periods, period_time) = settings.split_once('x')?;
+        ByoYomi::from(periods.parse().ok()?, period_time.parse().ok()?)
+    }
+}
+
+impl GameResult {
+    fn from_sgf(re: &str) -> Option<GameResult> {
+        if re == "0" || re.eq_ignore_ascii_case("draw") || re.eq_ignore_ascii_case("jigo") {
+            return Some(GameResult::Jigo);
+        }
+
+        let (color, how) = re.split_once('+')?;
+        let winner = match color {
+            "B" => PlayerColor::Black,
+            "W" => PlayerColor::White,
+            _ => return None
+        };
+
+        match how {
+            "R" | "Resign" => Some(GameResult::Resign(winner)),
+            "T" | "Time" => Some(GameResult::Time(winner)),
+            "F" | "Forfeit" => Some(GameResult::Forfeit(winner)),
+            "" | "?" => Some(GameResult::Count(winner, None)),
+            score => Some(GameResult::Count(winner, Some(Score::from_sgf(score)?)))
+        }
+    }
+}
+
+/// Parses results written in the format of SGF `RE` property, eg. `B+R` or `W+6.5`
+impl FromStr for GameResult {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<GameResult, String> {
+        GameResult::from_sgf(str).ok_or_else(|| format!("invalid result '{}'", str))
+    }
+}
+
+impl Display for SgfToGibError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SgfToGibError::Parse(e) => write!(f, "{}", e),
+            SgfToGibError::NoGame => write!(f, "no game in SGF file"),
+            SgfToGibError::InvalidProperty { name, value } => write!(f, "invalid value for {}: '{}'", name, value),
+            SgfToGibError::Unsupported { name, value } => write!(f, "{}[{}] can't be written to GIB and was left out", name, value),
+        }
+    }
+}
+
+impl Error for SgfToGibError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gib2sgf::gib_to_sgf;
+    use crate::gib::Gib;
+
+    const GIB: &str = r#"\HS
+\[GAMEGONGJE=65\]
+\[GAMEDATE=2020- 3-13-23-21-56\]
+\[GAMEPLACE=Tygem Baduk\]
+\[GAMEWHITENAME=TheWhite (2D)\]
+\[GAMEBLACKNAME=TheBlack (2D)\]
+\[GAMEINFOMAIN=GBKIND:3,GTYPE:0,GCDT:0,GTIME:600-30-3,GRLT:0,ZIPSU:35,DUM:0,GONGJE:65,TCNT:185,AUSZ:0\]
+\HE
+\GS
+2 1 0
+186 0 &4
+INI 0 1 0 &4
+STO 0 2 1 16 3 587 3
+STO 0 3 2 15 16
+SKI 0 4
+STO 0 5 2 3 3
+\GE
+"#;
+
+    fn round_trip(sgf: &str) -> String {
+        gib_to_sgf(&sgf_to_gib(sgf).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_writing_gib() {
+        let gib = sgf_to_gib("(;PB[TheBlack]BR[2D]PW[TheWhite]KM[6.5]DT[2020-03-13]RE[W+R]SO[Tygem Baduk]TM[600]OT[3x30 byo-yomi]\
+                              ;B[qd]BL[587]OB[3];W[pq];B[];W[dd](;B[cc])(;B[dc]))").unwrap();
+
+        assert_eq!(gib, "\\HS\r\n\
+                         \\[GAMEBLACKNAME=TheBlack (2D)\\]\r\n\
+                         \\[GAMEWHITENAME=TheWhite\\]\r\n\
+                         \\[GAMEGONGJE=65\\]\r\n\
+                         \\[GAMEDATE=2020- 3-13- 0- 0- 0\\]\r\n\
+                         \\[GAMEPLACE=Tygem Baduk\\]\r\n\
+                         \\[GAMEINFOMAIN=GTIME:600-30-3,GRLT:4,ZIPSU:0,GONGJE:65\\]\r\n\
+                         \\HE\r\n\
+                         \\GS\r\n\
+                         INI 0 1 0 &4\r\n\
+                         STO 0 2 1 16 3 587 3\r\n\
+                         STO 0 3 2 15 16\r\n\
+                         SKI 0 4\r\n\
+                         STO 0 5 2 3 3\r\n\
+                         STO 0 6 1 2 2\r\n\
+                         \\GE\r\n");
+    }
+
+    #[test]
+    fn test_round_trip_from_gib() {
+        let sgf = gib_to_sgf(GIB).unwrap();
+        assert_eq!(round_trip(&sgf), sgf);
+
+        let original = Gib::parse(GIB).unwrap();
+        let converted = Gib::parse(&sgf_to_gib(&sgf).unwrap()).unwrap();
+        assert_eq!(converted.get_moves(), original.get_moves());
+        assert_eq!(converted.get_result(), original.get_result());
+        assert_eq!(converted.get_time_control(), original.get_time_control());
+        assert_eq!(converted.get_time_left(0), original.get_time_left(0));
+    }
+
+    #[test]
+    fn test_round_trip_time_settings() {
+        for &(gtime, ot) in [("600-10-0", "OT[Fischer +10s]"), ("600-0-3", "OT[GTIME 600-0-3]"), ("600-30-3-10", "OT[GTIME 600-30-3-10]")].iter() {
+            let gib = format!("\\HS\n\\[GAMEINFOMAIN=GTIME:{}\\]\n\\HE\n\\GS\nINI 0 1 0 &4\n\\GE\n", gtime);
+            let sgf = gib_to_sgf(&gib).unwrap();
+            assert!(sgf.contains(&format!("TM[600]{}", ot)), "{}", sgf);
+            assert!(sgf_to_gib(&sgf).unwrap().contains(&format!("GTIME:{}", gtime)));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_handicaps() {
+        let fixed = gib_to_sgf("\\GS\nINI 0 1 4 &4\nSTO 0 2 2 16 3\n\\GE\n").unwrap();
+        assert_eq!(round_trip(&fixed), fixed);
+        assert!(sgf_to_gib(&fixed).unwrap().contains("INI 0 1 4 &4\r\nSTO 0 2 2 16 3\r\n"));
+
+        let free = gib_to_sgf("\\GS\nINI 0 1 2 &4\nSTO 0 2 1 2 2\nSTO 0 3 1 16 16\nSTO 0 4 2 16 3\n\\GE\n").unwrap();
+        assert!(free.contains("AB[cc][qq]"));
+        assert_eq!(round_trip(&free), free);
+    }
+
+    #[test]
+    fn test_small_board() {
+        let sgf = "(;SZ[9];B[ee];W[cc])";
+        let gib = sgf_to_gib(sgf).unwrap();
+        assert!(gib.contains("\\[GAMEBOARDSIZE=9\\]"));
+        assert!(round_trip(sgf).contains("SZ[9]"));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let collection = SgfCollection::parse("(;PB[TheBlack]BR[2D]PW[TheWhite]KM[6.5]DT[2020-03-13,14]RE[W+R]HA[2]TM[600]OT[3x30 byo-yomi]\
+                                               ;B[qd];W[pq](;B[cc])(;B[dc];W[dd]))").unwrap();
+        let nodes = collection.main_line();
+        let metadata = GibMetadata::from_sgf(nodes[0]);
+
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(metadata.black_nick.as_deref(), Some("TheBlack"));
+        assert_eq!(metadata.black_rank.as_deref(), Some("2D"));
+        assert_eq!(metadata.white_rank, None);
+        assert_eq!(metadata.date, LocalDate::parse_iso("2020-03-13").ok());
+        assert_eq!(metadata.result, Some(GameResult::Resign(PlayerColor::White)));
+        assert_eq!(metadata.handicap, Handicap::from(2));
+        assert_eq!(metadata.time_control, Some(TimeControl::new(600, ByoYomi::from(3, 30))));
+        assert_eq!(GibMetadata::from_sgf(&SgfNode::new()).komi, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(sgf_to_gib("(;B[aa]"), Err(SgfToGibError::Parse(_))));
+        assert!(matches!(sgf_to_gib("(;KM[foo])"), Err(SgfToGibError::InvalidProperty { .. })));
+        assert_eq!(sgf_to_gib("(;SZ[9];B[jj])").unwrap_err().to_string(), "invalid value for point: 'jj'");
+        assert!(matches!(sgf_to_gib("(;RE[X+R])"), Err(SgfToGibError::InvalidProperty { .. })));
+
+        let points: String = (0..256).map(|i| format!("[{}{}]", (b'a' + i as u8 % 19) as char, (b'a' + i as u8 / 19) as char)).collect();
+        assert_eq!(sgf_to_gib(&format!("(;AB{})", points)).unwrap_err().to_string(), "invalid value for AB: '256 points'");
+    }
+
+    #[test]
+    fn test_unsupported_properties() {
+        fn warnings(sgf: &str) -> Vec<String> {
+            sgf_to_gib_with_warnings(sgf).unwrap().warnings.iter().map(|w| w.to_string()).collect()
+        }
+
+        for sgf in &["(;RE[?])", "(;RE[Void])", "(;RE[])"] {
+            let conversion = sgf_to_gib_with_warnings(sgf).unwrap();
+            assert!(conversion.warnings.is_empty());
+            assert!(conversion.gib.contains("\\[GAMEINFOMAIN=\\]"));
+        }
+
+        assert_eq!(warnings("(;RE[Draw])"), vec!["RE[Draw] can't be written to GIB and was left out"]);
+        assert_eq!(warnings("(;RE[W+F])"), vec!["RE[W+F] can't be written to GIB and was left out"]);
+        assert_eq!(warnings("(;DT[2020-04])"), vec!["DT[2020-04] can't be written to GIB and was left out"]);
+        assert!(!sgf_to_gib("(;DT[2020-04])").unwrap().contains("GAMEDATE"));
+    }
+
+    #[test]
+    fn test_setup_stones() {
+        fn convert(sgf: &str) -> (String, Vec<String>) {
+            let conversion = sgf_to_gib_with_warnings(sgf).unwrap();
+            (gib_to_sgf(&conversion.gib).unwrap(), conversion.warnings.iter().map(|w| w.to_string()).collect())
+        }
+
+        let (sgf, warnings) = convert("(;AB[cc];W[dd])");
+        assert!(!sgf.contains("AB[") && sgf.contains(";W[dd]"));
+        assert_eq!(warnings, vec!["AB[cc] can't be written to GIB and was left out"]);
+
+        let (_, warnings) = convert("(;HA[1]AB[cc];B[dd])");
+        assert_eq!(warnings, vec!["AB[cc] can't be written to GIB and was left out"]);
+        let (_, warnings) = convert("(;HA[0]AB[cc];B[dd])");
+        assert_eq!(warnings, vec!["HA[0] can't be written to GIB and was left out", "AB[cc] can't be written to GIB and was left out"]);
+
+        let (sgf, warnings) = convert("(;AW[cc][dd];B[ee];AB[ff]AE[ee];W[gg])");
+        assert!(sgf.contains(";B[ee];W[gg])"));
+        assert_eq!(warnings, vec!["AW[cc][dd] can't be written to GIB and was left out",
+                                  "AB[ff] can't be written to GIB and was left out",
+                                  "AE[ee] can't be written to GIB and was left out"]);
+
+        // The stones are kept and the handicap follows them
+        let (sgf, warnings) = convert("(;HA[2]AB[dd][pp][dp];W[qd])");
+        assert!(sgf.contains("HA[3]") && sgf.contains("AB[dd][pp][dp]") && sgf.contains(";W[qd])"), "{}", sgf);
+        assert_eq!(warnings, vec!["HA[2] can't be written to GIB and was left out"]);
+        let (_, warnings) = convert("(;HA[2]AB[dd][pp];W[qd])");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parsing_sgf_values() {
+        assert_eq!(GameResult::from_sgf("B+R"), Some(GameResult::Resign(PlayerColor::Black)));
+        assert_eq!(GameResult::from_sgf("W+3.5"), Some(GameResult::Count(PlayerColor::White, Some(Score::new(3.5)))));
+        assert_eq!("W+T".parse(), Ok(GameResult::Time(PlayerColor::White)));
+        assert_eq!("X+R".parse::<GameResult>(), Err("invalid result 'X+R'".to_string()));
+        assert_eq!(GameResult::from_sgf("W+"), Some(GameResult::Count(PlayerColor::White, None)));
+        assert_eq!(GameResult::from_sgf("B+Time"), Some(GameResult::Time(PlayerColor::Black)));
+        assert_eq!(GameResult::from_sgf("0"), Some(GameResult::Jigo));
+        assert_eq!(GameResult::from_sgf("Void"), None);
+        assert_eq!(LocalDate::from_sgf("2020-03-13,14"), LocalDate::ymd(2020, 3, 13).ok());
+        assert_eq!(ByoYomi::from_sgf("5x60 byo-yomi"), ByoYomi::from(5, 60));
+    }
+}
@@ -0,0 +1,291 @@
+//! Parsing SGF files into `SgfCollection`.
+//!
+//! Follows the FF[4] grammar described at https://www.red-bean.com/sgf/sgf4.html
+//! Property values are unescaped while parsing, so the resulting nodes contain the
+//! same values that would have been used when building the tree programmatically.
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::sgf::{SgfCollection, SgfTree, SgfNode};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SgfParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    pub message: String,
+}
+
+type SgfResult<T> = Result<T, SgfParseError>;
+
+impl SgfCollection {
+    pub fn parse(str: &str) -> SgfResult<SgfCollection> {
+        SgfParser::new(str).parse_collection()
+    }
+}
+
+struct SgfParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> SgfParser<'a> {
+    fn new(str: &'a str) -> SgfParser<'a> {
+        SgfParser { chars: str.chars().peekable(), line: 1, column: 1 }
+    }
+
+    /// `Collection = GameTree { GameTree }`
+    fn parse_collection(&mut self) -> SgfResult<SgfCollection> {
+        let mut collection = SgfCollection::new();
+
+        self.skip_whitespace();
+        while self.peek().is_some() {
+            collection.add_tree(self.parse_tree()?);
+            self.skip_whitespace();
+        }
+
+        if collection.trees().is_empty() {
+            return Err(self.error("expected '(', but found end of file"));
+        }
+
+        Ok(collection)
+    }
+
+    /// `GameTree = "(" Sequence { GameTree } ")"`
+    fn parse_tree(&mut self) -> SgfResult<SgfTree> {
+        let mut tree = SgfTree::new();
+
+        self.expect('(')?;
+        self.skip_whitespace();
+
+        if self.peek() != Some(';') {
+            return Err(self.unexpected("';'"));
+        }
+
+        while self.peek() == Some(';') {
+            tree.add_node(self.parse_node()?);
+            self.skip_whitespace();
+        }
+
+        while self.peek() == Some('(') {
+            tree.add_child(self.parse_tree()?);
+            self.skip_whitespace();
+        }
+
+        self.expect(')')?;
+        Ok(tree)
+    }
+
+    /// `Node = ";" { Property }`
+    fn parse_node(&mut self) -> SgfResult<SgfNode> {
+        let mut node = SgfNode::new();
+
+        self.expect(';')?;
+        self.skip_whitespace();
+
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+
+            let (line, column) = (self.line, self.column);
+            let (name, values) = self.parse_property()?;
+            if node.get_property_list(&name).is_some() {
+                return Err(SgfParseError { line, column, message: format!("duplicate property '{}'", name) });
+            }
+            node.set_property_list(&name, values);
+            self.skip_whitespace();
+        }
+
+        Ok(node)
+    }
+
+    /// `Property = PropIdent PropValue { PropValue }`
+    ///
+    /// Lowercase letters in identifiers are allowed by older versions of the format
+    /// (eg. `AddBlack`) and are ignored.
+    fn parse_property(&mut self) -> SgfResult<(String, Vec<String>)> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            if c.is_ascii_uppercase() {
+                name.push(c);
+            }
+            self.next();
+        }
+
+        if name.is_empty() {
+            return Err(self.error("property identifier must contain uppercase letters"));
+        }
+
+        self.skip_whitespace();
+        if self.peek() != Some('[') {
+            return Err(self.unexpected("'['"));
+        }
+
+        let mut values = Vec::new();
+        while self.peek() == Some('[') {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+        }
+
+        Ok((name, values))
+    }
+
+    /// `PropValue = "[" CValueType "]"`, resolving escapes and soft line breaks
+    fn parse_value(&mut self) -> SgfResult<String> {
+        self.expect('[')?;
+
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some(']') => return Ok(value),
+                Some('\\') => match self.next() {
+                    // Soft line break, removed from the value
+                    Some('\n') => self.skip_if('\r'),
+                    Some('\r') => self.skip_if('\n'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+
+        Err(self.error("unterminated property value"))
+    }
+
+    fn expect(&mut self, expected: char) -> SgfResult<()> {
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", expected)))
+        }
+    }
+
+    fn skip_if(&mut self, c: char) {
+        if self.peek() == Some(c) {
+            self.next();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn unexpected(&mut self, expected: &str) -> SgfParseError {
+        match self.peek() {
+            Some(c) => self.error(&format!("expected {}, but found '{}'", expected, c)),
+            None => self.error(&format!("expected {}, but found end of file", expected)),
+        }
+    }
+
+    fn error(&self, message: &str) -> SgfParseError {
+        SgfParseError { line: self.line, column: self.column, message: message.to_string() }
+    }
+}
+
+impl Display for SgfParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for SgfParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(str: &str) -> (usize, usize, String) {
+        let error = SgfCollection::parse(str).err().unwrap();
+        (error.line, error.column, error.message)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sgf = "(;GM[1]FF[4]SZ[19]PB[Black]PW[White];B[pd];W[dp](;B[pq];W[dd])(;B[dd]))(;GM[1];B[aa])";
+        assert_eq!(SgfCollection::parse(sgf).unwrap().to_sgf(), sgf);
+    }
+
+    #[test]
+    fn test_structure() {
+        let collection = SgfCollection::parse("(;FF[4];B[pd](;W[dp])(;W[dd];B[pp](;W[cc])))").unwrap();
+
+        let tree = &collection.trees()[0];
+        assert_eq!(tree.nodes().len(), 2);
+        assert_eq!(tree.nodes()[1].get_property("B"), Some("pd"));
+        assert_eq!(tree.children().len(), 2);
+        assert_eq!(tree.children()[0].nodes()[0].get_property("W"), Some("dp"));
+        assert_eq!(tree.children()[1].nodes().len(), 2);
+        assert_eq!(tree.children()[1].children()[0].nodes()[0].get_property("W"), Some("cc"));
+    }
+
+    #[test]
+    fn test_whitespace_and_lists() {
+        let collection = SgfCollection::parse("\n(\n ;FF[4] AB [dd]\n [pp][dp]\r\n ;\tW[]\n)\n").unwrap();
+
+        let nodes = collection.trees()[0].nodes();
+        assert_eq!(nodes[0].get_property_list("AB"), Some(&vec!["dd".to_string(), "pp".to_string(), "dp".to_string()]));
+        assert_eq!(nodes[1].get_property("W"), Some(""));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let collection = SgfCollection::parse("(;C[a \\] b \\\\ c \\: d]PB[foo\\\nbar\\\r\nbaz]GC[first\nsecond])").unwrap();
+
+        let node = &collection.trees()[0].nodes()[0];
+        assert_eq!(node.get_property("C"), Some("a ] b \\ c : d"));
+        assert_eq!(node.get_property("PB"), Some("foobarbaz"));
+        assert_eq!(node.get_property("GC"), Some("first\nsecond"));
+    }
+
+    #[test]
+    fn test_lowercase_identifiers() {
+        let collection = SgfCollection::parse("(;AddBlack[dd]CoPyright[foo])").unwrap();
+
+        let node = &collection.trees()[0].nodes()[0];
+        assert_eq!(node.get_property("AB"), Some("dd"));
+        assert_eq!(node.get_property("CP"), Some("foo"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_error(""), (1, 1, "expected '(', but found end of file".to_string()));
+        assert_eq!(parse_error("(;B[aa]"), (1, 8, "expected ')', but found end of file".to_string()));
+        assert_eq!(parse_error("()"), (1, 2, "expected ';', but found ')'".to_string()));
+        assert_eq!(parse_error("(;FF[4]\n;B[aa\n"), (3, 1, "unterminated property value".to_string()));
+        assert_eq!(parse_error("(;FF[4]\n  ;B[aa]B[bb])"), (2, 9, "duplicate property 'B'".to_string()));
+        assert_eq!(parse_error("(;FF 4)"), (1, 6, "expected '[', but found '4'".to_string()));
+        assert_eq!(parse_error("(;ff[4])"), (1, 5, "property identifier must contain uppercase letters".to_string()));
+        assert_eq!(parse_error("(;B[aa]) x"), (1, 10, "expected '(', but found 'x'".to_string()));
+    }
+}
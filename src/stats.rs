@@ -0,0 +1,231 @@
+//! Win/loss statistics of a player, computed from the games of an archive
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use gib2sgf::{GameResult, LocalDate, PlayerColor};
+use crate::index::IndexEntry;
+
+/// Wins, losses and other outcomes of a group of games
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Record {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    /// Games without a known result
+    pub unknown: usize,
+}
+
+/// Statistics of a single player
+#[derive(Debug, PartialEq)]
+pub struct PlayerStats {
+    pub player: String,
+    pub total: Record,
+    pub black: Record,
+    pub white: Record,
+    /// Records against opponents of each rank, from the weakest rank to the strongest
+    pub by_opponent_rank: Vec<(String, Record)>,
+    /// Records by how the game ended: resign, count, time, forfeit or draw
+    pub by_result_type: Vec<(&'static str, Record)>,
+    /// Dates when the rank of the player changed, starting from the first game
+    pub rank_progression: Vec<(LocalDate, String)>,
+    /// Average number of moves per game
+    pub average_moves: f64,
+}
+
+impl Record {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws + self.unknown
+    }
+
+    /// Percentage of wins out of decided games
+    pub fn win_rate(&self) -> Option<f64> {
+        let decided = self.wins + self.losses;
+        if decided > 0 { Some(100.0 * self.wins as f64 / decided as f64) } else { None }
+    }
+
+    fn add(&mut self, color: PlayerColor, result: Option<GameResult>) {
+        match result {
+            Some(GameResult::Jigo) => self.draws += 1,
+            Some(result) if result.winner() == Some(color) => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.unknown += 1,
+        }
+    }
+}
+
+impl PlayerStats {
+    /// Computes the statistics from the games where the player played either colour.
+    /// Returns None if the player didn't play any of the games.
+    pub fn new(player: &str, entries: &[IndexEntry]) -> Option<PlayerStats> {
+        let mut games: Vec<(&IndexEntry, PlayerColor)> = entries.iter()
+            .filter_map(|entry| Some((entry, entry.color_of(player)?)))
+            .collect();
+        if games.is_empty() {
+            return None;
+        }
+        games.sort_by_key(|(entry, _)| entry.date);
+
+        let mut stats = PlayerStats {
+            player: player.to_string(),
+            total: Record::default(),
+            black: Record::default(),
+            white: Record::default(),
+            by_opponent_rank: Vec::new(),
+            by_result_type: Vec::new(),
+            rank_progression: Vec::new(),
+            average_moves: 0.0,
+        };
+        let mut by_opponent_rank = BTreeMap::new();
+        let mut by_result_type = BTreeMap::new();
+        let mut moves = 0;
+
+        for &(entry, color) in &games {
+            stats.total.add(color, entry.result);
+            color.pick(&mut stats.black, &mut stats.white).add(color, entry.result);
+
+            let opponent_rank = entry.rank(color.opponent()).unwrap_or("?").to_ascii_uppercase();
+            by_opponent_rank.entry((rank_order(&opponent_rank), opponent_rank)).or_insert_with(Record::default).add(color, entry.result);
+            if let Some(result) = entry.result {
+                by_result_type.entry(result_type(result)).or_insert_with(Record::default).add(color, entry.result);
+            }
+
+            if let (Some(date), Some(rank)) = (entry.date, entry.rank(color)) {
+                if stats.rank_progression.last().is_none_or(|(_, last)| !last.eq_ignore_ascii_case(rank)) {
+                    stats.rank_progression.push((date, rank.to_string()));
+                }
+            }
+            moves += entry.moves;
+        }
+
+        stats.by_opponent_rank = by_opponent_rank.into_iter().map(|((_, rank), record)| (rank, record)).collect();
+        stats.by_result_type = by_result_type.into_iter().map(|((_, name), record)| (name, record)).collect();
+        stats.average_moves = moves as f64 / games.len() as f64;
+        Some(stats)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        let total = &self.total;
+        let _ = writeln!(result, "{}: {} games, {} wins, {} losses{}", self.player, total.games(), total.wins, total.losses,
+                         if total.draws > 0 { format!(", {} draws", total.draws) } else { String::new() });
+        let _ = writeln!(result, "Average length: {:.1} moves", self.average_moves);
+
+        write_records(&mut result, "By colour", &[("Black", self.black), ("White", self.white)]);
+        let by_rank: Vec<(&str, Record)> = self.by_opponent_rank.iter().map(|(rank, record)| (rank.as_str(), *record)).collect();
+        write_records(&mut result, "By opponent rank", &by_rank);
+        write_records(&mut result, "By result", &self.by_result_type);
+
+        if !self.rank_progression.is_empty() {
+            let _ = writeln!(result, "\nRank progression");
+            for (date, rank) in &self.rank_progression {
+                let _ = writeln!(result, "  {}  {}", date.iso_string(), rank);
+            }
+        }
+        result
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let record = |record: &Record| serde_json::json!({
+            "games": record.games(),
+            "wins": record.wins,
+            "losses": record.losses,
+            "draws": record.draws,
+            "unknown": record.unknown,
+            "win_rate": record.win_rate(),
+        });
+        let records = |records: &mut dyn Iterator<Item=(&str, &Record)>| records
+            .map(|(name, r)| serde_json::json!({ "name": name, "record": record(r) }))
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "player": self.player,
+            "total": record(&self.total),
+            "black": record(&self.black),
+            "white": record(&self.white),
+            "by_opponent_rank": records(&mut self.by_opponent_rank.iter().map(|(rank, r)| (rank.as_str(), r))),
+            "by_result_type": records(&mut self.by_result_type.iter().map(|(name, r)| (*name, r))),
+            "rank_progression": self.rank_progression.iter()
+                .map(|(date, rank)| serde_json::json!({ "date": date.iso_string(), "rank": rank }))
+                .collect::<Vec<_>>(),
+            "average_moves": self.average_moves,
+        })
+    }
+}
+
+fn write_records(result: &mut String, title: &str, records: &[(&str, Record)]) {
+    let _ = writeln!(result, "\n{:16}  Games  Wins  Losses  Win %", title);
+    for (name, record) in records {
+        let win_rate = record.win_rate().map_or("-".to_string(), |rate| format!("{:.1}", rate));
+        let _ = writeln!(result, "  {:14}  {:>5}  {:>4}  {:>6}  {:>5}", name, record.games(), record.wins, record.losses, win_rate);
+    }
+}
+
+/// Orders ranks from 30K to 1K, 1D to 9D and then professional ranks
+fn rank_order(rank: &str) -> (u8, i32) {
+    let number = |suffix: char| rank.strip_suffix(suffix).and_then(|n| n.parse::<i32>().ok());
+    if let Some(kyu) = number('K') {
+        (0, -kyu)
+    } else if let Some(dan) = number('D') {
+        (1, dan)
+    } else if let Some(pro) = number('P') {
+        (2, pro)
+    } else {
+        (3, 0)
+    }
+}
+
+/// Describes how the game ended, with a key for ordering the types
+fn result_type(result: GameResult) -> (u8, &'static str) {
+    match result {
+        GameResult::Resign(_) => (0, "resign"),
+        GameResult::Count(_, _) => (1, "count"),
+        GameResult::Time(_) => (2, "time"),
+        GameResult::Forfeit(_) => (3, "forfeit"),
+        GameResult::Jigo => (4, "draw"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::entry as game;
+
+    fn games() -> Vec<IndexEntry> {
+        vec![
+            game(("komula", "4D"), ("zangpung", "4D"), "2020-04-01", "B+R", 200),
+            game(("fingersid", "2D"), ("komula", "2D"), "2020-03-14", "W+3.5", 150),
+            game(("ysoo5", "5D"), ("komula", "4D"), "2020-04-14", "B+T", 100),
+            game(("komula", "4D"), ("x", "15K"), "2020-04-20", "0", 50),
+            game(("someone", "1D"), ("else", "1D"), "2020-04-21", "B+R", 10),
+        ]
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = PlayerStats::new("Komula", &games()).unwrap();
+
+        assert_eq!(stats.total, Record { wins: 2, losses: 1, draws: 1, unknown: 0 });
+        assert_eq!(stats.black, Record { wins: 1, losses: 0, draws: 1, unknown: 0 });
+        assert_eq!(stats.white, Record { wins: 1, losses: 1, draws: 0, unknown: 0 });
+        assert_eq!(stats.total.win_rate().map(|r| r.round()), Some(67.0));
+        assert_eq!(stats.average_moves, 125.0);
+
+        let ranks: Vec<&str> = stats.by_opponent_rank.iter().map(|(rank, _)| rank.as_str()).collect();
+        assert_eq!(ranks, vec!["15K", "2D", "4D", "5D"]);
+        let types: Vec<(&str, usize, usize)> = stats.by_result_type.iter().map(|(name, r)| (*name, r.wins, r.losses)).collect();
+        assert_eq!(types, vec![("resign", 1, 0), ("count", 1, 0), ("time", 0, 1), ("draw", 0, 0)]);
+
+        let progression: Vec<(String, &str)> = stats.rank_progression.iter().map(|(d, r)| (d.iso_string(), r.as_str())).collect();
+        assert_eq!(progression, vec![("2020-03-14".to_string(), "2D"), ("2020-04-01".to_string(), "4D")]);
+
+        assert!(PlayerStats::new("nobody", &games()).is_none());
+    }
+
+    #[test]
+    fn test_text() {
+        let text = PlayerStats::new("komula", &games()).unwrap().to_text();
+        assert!(text.starts_with("komula: 4 games, 2 wins, 1 losses, 1 draws\nAverage length: 125.0 moves\n"));
+        assert!(text.contains("\nBy colour         Games  Wins  Losses  Win %\n  Black               2     1       0  100.0\n"));
+        assert!(text.contains("\nRank progression\n  2020-03-14  2D\n  2020-04-01  4D\n"));
+    }
+}
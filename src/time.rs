@@ -1,4 +1,10 @@
-#[derive(Eq, PartialEq, Debug)]
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
 pub struct LocalDate {
     year: u16,
     month: u8,
@@ -7,10 +13,35 @@ pub struct LocalDate {
 
 impl LocalDate {
     pub fn ymd(year: u16, month: u8, day: u8) -> Result<LocalDate, String> {
-        if month >= 1 && month <= 12 && day >= 1 && day <= days_in_month(month, year) {
+        if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(month, year) {
             Ok(LocalDate { year, month, day })
         } else {
-            Err(format!("invalid month {:04}-{:02}-{:02}", year, month, day))
+            Err(format!("invalid date {:04}-{:02}-{:02}", year, month, day))
+        }
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Parses dates of form `YYYY-MM-DD`
+    pub fn parse_iso(str: &str) -> Result<LocalDate, String> {
+        let parts: Vec<&str> = str.split('-').collect();
+        if parts.len() != 3 || !parts.iter().all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit())) {
+            return Err(format!("invalid date '{}'", str));
+        }
+
+        match (parts[0].parse(), parts[1].parse(), parts[2].parse()) {
+            (Ok(year), Ok(month), Ok(day)) => LocalDate::ymd(year, month, day),
+            _ => Err(format!("invalid date '{}'", str))
         }
     }
 
@@ -19,6 +50,104 @@ impl LocalDate {
     }
 }
 
+/// Date and time without timezone information
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct LocalDateTime {
+    date: LocalDate,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl LocalDateTime {
+    pub fn new(date: LocalDate, hour: u8, minute: u8, second: u8) -> Result<LocalDateTime, String> {
+        if hour < 24 && minute < 60 && second < 60 {
+            Ok(LocalDateTime { date, hour, minute, second })
+        } else {
+            Err(format!("invalid time {:02}:{:02}:{:02}", hour, minute, second))
+        }
+    }
+
+    pub fn date(&self) -> LocalDate {
+        self.date
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Moves the time by given minutes, changing the date if needed.
+    /// Returns None if the date would be outside the years 0 to 65535.
+    pub fn plus_minutes(&self, minutes: i32) -> Option<LocalDateTime> {
+        let total = (i32::from(self.hour) * 60 + i32::from(self.minute)).checked_add(minutes)?;
+        let minute_of_day = total.rem_euclid(24 * 60);
+
+        Some(LocalDateTime {
+            date: self.date.plus_days(total.div_euclid(24 * 60))?,
+            hour: (minute_of_day / 60) as u8,
+            minute: (minute_of_day % 60) as u8,
+            second: self.second,
+        })
+    }
+
+    pub fn iso_string(&self) -> String {
+        format!("{} {:02}:{:02}:{:02}", self.date.iso_string(), self.hour, self.minute, self.second)
+    }
+}
+
+impl LocalDate {
+    /// Returns the date moved by given days, or None if it would be outside the years 0 to 65535
+    pub fn plus_days(&self, days: i32) -> Option<LocalDate> {
+        let mut date = *self;
+        for _ in 0..days.unsigned_abs() {
+            date = if days > 0 { date.next_day()? } else { date.previous_day()? };
+        }
+        Some(date)
+    }
+
+    fn next_day(&self) -> Option<LocalDate> {
+        if self.day < days_in_month(self.month, self.year) {
+            Some(LocalDate { day: self.day + 1, ..*self })
+        } else if self.month < 12 {
+            Some(LocalDate { month: self.month + 1, day: 1, ..*self })
+        } else {
+            Some(LocalDate { year: self.year.checked_add(1)?, month: 1, day: 1 })
+        }
+    }
+
+    fn previous_day(&self) -> Option<LocalDate> {
+        if self.day > 1 {
+            Some(LocalDate { day: self.day - 1, ..*self })
+        } else if self.month > 1 {
+            Some(LocalDate { month: self.month - 1, day: days_in_month(self.month - 1, self.year), ..*self })
+        } else {
+            Some(LocalDate { year: self.year.checked_sub(1)?, month: 12, day: 31 })
+        }
+    }
+}
+
+impl TryFrom<String> for LocalDate {
+    type Error = String;
+
+    fn try_from(str: String) -> Result<LocalDate, String> {
+        LocalDate::parse_iso(&str)
+    }
+}
+
+impl From<LocalDate> for String {
+    fn from(date: LocalDate) -> String {
+        date.iso_string()
+    }
+}
+
 fn days_in_month(month: u8, year: u16) -> u8 {
     match month {
         1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
@@ -41,4 +170,35 @@ mod tests {
 
         assert_eq!(date.iso_string(), "2020-04-07");
     }
+
+    #[test]
+    fn local_date_time_arithmetic() {
+        let time = LocalDateTime::new(LocalDate::ymd(2020, 12, 31).unwrap(), 23, 21, 56).unwrap();
+
+        let plus_minutes = |minutes| time.plus_minutes(minutes).unwrap().iso_string();
+
+        assert_eq!(time.plus_minutes(0), Some(time));
+        assert_eq!(plus_minutes(60), "2021-01-01 00:21:56");
+        assert_eq!(plus_minutes(-9 * 60), "2020-12-31 14:21:56");
+        assert_eq!(plus_minutes(-24 * 60 * 305), "2020-03-01 23:21:56");
+        assert_eq!(plus_minutes(-24 * 60 * 306), "2020-02-29 23:21:56");
+        assert_eq!(time.plus_minutes(i32::MAX), None);
+        assert!(LocalDateTime::new(time.date(), 24, 0, 0).is_err());
+
+        // Years outside u16 can't be represented
+        let first = LocalDateTime::new(LocalDate::ymd(0, 1, 1).unwrap(), 1, 0, 0).unwrap();
+        assert_eq!(first.plus_minutes(-9 * 60), None);
+        let last = LocalDateTime::new(LocalDate::ymd(65535, 12, 31).unwrap(), 23, 0, 0).unwrap();
+        assert_eq!(last.plus_minutes(60), None);
+        assert_eq!(last.date().plus_days(-1), LocalDate::ymd(65535, 12, 30).ok());
+    }
+
+    #[test]
+    fn local_date_parse() {
+        assert_eq!(LocalDate::parse_iso("2020-04-07"), LocalDate::ymd(2020, 4, 7));
+        assert_eq!(LocalDate::parse_iso("2020-4-7"), LocalDate::ymd(2020, 4, 7));
+        assert!(LocalDate::parse_iso("2020-02-30").is_err());
+        assert_eq!(LocalDate::parse_iso("2020-04"), Err("invalid date '2020-04'".to_string()));
+        assert!(LocalDate::parse_iso("2020-+4-07").is_err());
+    }
 }
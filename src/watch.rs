@@ -0,0 +1,166 @@
+//! Watching a directory for GIB files written by the Tygem client
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant, SystemTime};
+use notify::{Config, Event, EventKind, PollWatcher, RecursiveMode, Watcher};
+use crate::files::{is_gib_file, InputFile};
+
+/// How long a file must stay unchanged before it's considered completely written
+const STABLE_TIME: Duration = Duration::from_secs(2);
+
+/// How often directories are scanned when file system events are not available
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often pending files are checked when there are no events
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Size and modification time of a file, used to notice when it's still being written
+type FileState = (u64, Option<SystemTime>);
+
+/// Files that have been changed, but not yet converted
+#[derive(Debug, Default)]
+pub struct PendingFiles {
+    files: HashMap<PathBuf, (FileState, Instant)>,
+}
+
+impl PendingFiles {
+    /// Records the current state of a file, restarting the wait if the file has changed
+    pub fn update(&mut self, path: &Path, state: FileState, now: Instant) {
+        match self.files.get_mut(path) {
+            Some((old_state, changed)) => if *old_state != state {
+                *old_state = state;
+                *changed = now;
+            },
+            None => {
+                self.files.insert(path.to_path_buf(), (state, now));
+            }
+        }
+    }
+
+    /// Re-reads the state of all pending files, forgetting the ones that have disappeared
+    fn refresh(&mut self, now: Instant) {
+        let paths: Vec<PathBuf> = self.files.keys().cloned().collect();
+        for path in paths {
+            match file_state(&path) {
+                Some(state) => self.update(&path, state, now),
+                None => { self.files.remove(&path); }
+            }
+        }
+    }
+
+    /// Removes and returns the files that have stayed unchanged long enough, in order
+    pub fn take_stable(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut stable: Vec<PathBuf> = self.files.iter()
+            .filter(|(_, (_, changed))| now.duration_since(*changed) >= STABLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        stable.sort();
+
+        for path in &stable {
+            self.files.remove(path);
+        }
+        stable
+    }
+}
+
+/// Watches the directory recursively and calls `convert` for each GIB file once it has
+/// been completely written. `scan` is called once the watcher has started, so that no files
+/// are missed between scanning the directory and watching it. Runs until the watcher stops.
+pub fn watch(dir: &Path, scan: &dyn Fn(), convert: &dyn Fn(InputFile)) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let _watcher = start_watcher(dir, sender)?;
+    let mut pending = PendingFiles::default();
+    scan();
+
+    // Events may have absolute paths even if the directory was given as a relative path
+    let canonical_dir = dir.canonicalize()?;
+
+    loop {
+        match receiver.recv_timeout(CHECK_INTERVAL) {
+            Ok(Ok(event)) => if is_write(&event) {
+                for path in event.paths.iter().filter(|p| is_gib_path(p)) {
+                    if let Some(state) = file_state(path) {
+                        pending.update(path, state, Instant::now());
+                    }
+                }
+            },
+            Ok(Err(e)) => eprintln!("Warning {:?}: {}", dir, e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let now = Instant::now();
+        pending.refresh(now);
+        for path in pending.take_stable(now) {
+            let relative_path = path.strip_prefix(dir).or_else(|_| path.strip_prefix(&canonical_dir))
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| path.file_name().map(PathBuf::from).unwrap_or_else(|| path.clone()));
+            convert(InputFile { path, relative_path });
+        }
+    }
+}
+
+/// Uses inotify or the native watcher of the platform, and falls back to polling if it
+/// can't be used, eg. on network drives or when inotify watches are exhausted
+fn start_watcher(dir: &Path, sender: mpsc::Sender<notify::Result<Event>>) -> notify::Result<Box<dyn Watcher>> {
+    let native = notify::recommended_watcher(sender.clone()).and_then(|mut watcher| {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+
+    match native {
+        Ok(watcher) => Ok(Box::new(watcher)),
+        Err(e) => {
+            eprintln!("Warning {:?}: {}, polling every {} seconds instead", dir, e, POLL_INTERVAL.as_secs());
+            let mut watcher = PollWatcher::new(sender, Config::default().with_poll_interval(POLL_INTERVAL))?;
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+            Ok(Box::new(watcher))
+        }
+    }
+}
+
+fn is_write(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any)
+}
+
+fn is_gib_path(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()).is_some_and(is_gib_file)
+}
+
+fn file_state(path: &Path) -> Option<FileState> {
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_files() {
+        let start = Instant::now();
+        let mut pending = PendingFiles::default();
+
+        pending.update(Path::new("b.gib"), (10, None), start);
+        pending.update(Path::new("a.gib"), (10, None), start);
+        assert!(pending.take_stable(start + Duration::from_secs(1)).is_empty());
+
+        // Still being written, so the wait starts again
+        pending.update(Path::new("a.gib"), (20, None), start + Duration::from_secs(1));
+        pending.update(Path::new("b.gib"), (10, None), start + Duration::from_secs(1));
+        assert_eq!(pending.take_stable(start + STABLE_TIME), vec![PathBuf::from("b.gib")]);
+
+        assert!(pending.take_stable(start + STABLE_TIME).is_empty());
+        assert_eq!(pending.take_stable(start + Duration::from_secs(1) + STABLE_TIME), vec![PathBuf::from("a.gib")]);
+    }
+
+    #[test]
+    fn test_is_gib_path() {
+        assert!(is_gib_path(Path::new("dir/game.gib")));
+        assert!(is_gib_path(Path::new("game.gib.dll")));
+        assert!(!is_gib_path(Path::new("dir/game.sgf")));
+    }
+}